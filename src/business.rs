@@ -1,22 +1,32 @@
 #[allow(unused)]
 use ic_canister_kit::common::once::call_once_guard;
 #[allow(unused)]
+use ic_canister_kit::common::trap;
+#[allow(unused)]
 use ic_canister_kit::identity::caller;
 
+use crate::certification::update_certified_assets;
+
 #[allow(unused)]
 use crate::stable::*;
 #[allow(unused)]
 use crate::types::*;
 
+// ! 原来的接口出错直接 trap, 保持不变
+// ! _v2 接口返回 StorageError, 权限和维护状态也作为错误返回
+
 // 查询
 #[ic_cdk::query(guard = "has_business_upload")]
 fn business_hashed_find() -> bool {
     with_state(|s| s.business_hashed_find())
 }
-#[ic_cdk::update(guard = "has_business_upload")]
-fn business_hashed_update(hashed: bool) {
-    let _guard = call_once_guard(); // post 接口应该拦截
+#[ic_cdk::query]
+fn business_hashed_find_v2() -> Result<bool, StorageError> {
+    check_business_upload()?;
+    Ok(with_state(|s| s.business_hashed_find()))
+}
 
+fn inner_hashed_update(hashed: bool) {
     let old = with_state(|s| s.business_hashed_find());
 
     if old == hashed {
@@ -35,41 +45,396 @@ fn business_hashed_update(hashed: bool) {
         arg_content,
     )
 }
+#[ic_cdk::update(guard = "has_business_upload")]
+fn business_hashed_update(hashed: bool) {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    inner_hashed_update(hashed)
+}
+#[ic_cdk::update]
+fn business_hashed_update_v2(hashed: bool) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload()?;
+    inner_hashed_update(hashed);
+    Ok(())
+}
 
 // 查询
 #[ic_cdk::query(guard = "has_business_query")]
 fn business_files() -> Vec<QueryFile> {
-    with_state(|s| s.business_files())
+    with_state(|s| s.business_files(&caller()))
+}
+#[ic_cdk::query]
+fn business_files_v2() -> Result<Vec<QueryFile>, StorageError> {
+    check_business_query()?;
+    Ok(with_state(|s| s.business_files(&caller())))
+}
+
+// 分页查询, 按路径的字典序, 可以按前缀过滤
+#[ic_cdk::query]
+fn business_files_by_page(
+    page: QueryPage,
+    prefix: Option<String>,
+    cursor: Option<String>, // 上一页返回的游标, 有游标时忽略页码, 也不统计总数
+    sort: Option<FileSort>,
+) -> Result<QueryFilePage, StorageError> {
+    check_business_query()?;
+    with_state(|s| {
+        s.business_files_by_page(
+            &page,
+            prefix.unwrap_or_default(),
+            cursor,
+            sort.unwrap_or_default(),
+            &caller(),
+        )
+    })
+}
+
+// 列出目录的直接子项, 不展开子目录, 按游标分页
+#[ic_cdk::query]
+fn business_list_dir(
+    path: String,
+    cursor: Option<String>, // 上一页返回的游标
+    limit: u32,
+) -> Result<QueryDir, StorageError> {
+    check_business_query()?;
+    with_state(|s| s.business_list_dir(path, cursor, limit, &caller()))
 }
 
 #[ic_cdk::query(guard = "has_business_query")]
 fn business_download(path: String) -> Vec<u8> {
+    trap(check_business_read(&path));
+    trap(with_state(|s| s.business_download(path)))
+}
+#[ic_cdk::query]
+fn business_download_v2(path: String) -> Result<Vec<u8>, StorageError> {
+    check_business_read(&path)?;
     with_state(|s| s.business_download(path))
 }
 
-// 下载数据数据
+// 下载数据数据, 可以指定历史版本
 #[ic_cdk::query(guard = "has_business_query")]
-fn business_download_by(path: String, offset: u64, size: u64) -> Vec<u8> {
-    with_state(|s| s.business_download_by(path, offset, size))
+fn business_download_by(path: String, offset: u64, size: u64, version: Option<u64>) -> Vec<u8> {
+    trap(check_business_read(&path));
+    trap(with_state(|s| s.business_download_by(path, offset, size, version)))
+}
+#[ic_cdk::query]
+fn business_download_by_v2(
+    path: String,
+    offset: u64,
+    size: u64,
+    version: Option<u64>,
+) -> Result<Vec<u8>, StorageError> {
+    check_business_read(&path)?;
+    with_state(|s| s.business_download_by(path, offset, size, version))
+}
+
+// 签发有时效的下载链接, 持有链接不需要访问控制允许也可以通过 http 下载
+// 指定历史版本时, 链接只能下载该版本
+#[ic_cdk::update]
+async fn business_sign_url(path: String, ttl: DurationNanos, version: Option<u64>) -> Result<SignedUrl, StorageError> {
+    check_business_read(&path)?; // 只能签发自己能读取的文件
+
+    let random = if with_state(|s| s.business_url_secret_ready()) {
+        None
+    } else {
+        let random = ic_canister_kit::number::random::random().await; // 密钥必须不可预测
+        Some(random.map_err(|err| StorageError::Internal(err.to_string()))?)
+    };
+
+    let _guard = call_once_guard(); // post 接口应该拦截, 不能跨越 await
+
+    let caller = caller();
+    let arg_content = format!("sign url: {path} version: {version:?} ttl: {}", ttl.into_inner()); // * 记录参数内容
+
+    let now = ic_canister_kit::times::now();
+    with_mut_state(
+        |s, _done| s.business_sign_url(path, version, ttl, now, random),
+        caller,
+        RecordTopics::SignUrl.topic(),
+        arg_content,
+    )
+}
+
+// 文件的历史版本, 新的版本在前
+#[ic_cdk::query]
+fn business_file_versions(path: String) -> Result<Vec<QueryFileVersion>, StorageError> {
+    check_business_read(&path)?;
+    with_state(|s| s.business_file_versions(path))
+}
+
+#[ic_cdk::query]
+fn business_version_retention() -> Result<VersionRetention, StorageError> {
+    check_business_query()?;
+    Ok(with_state(|s| s.business_version_retention()))
 }
 
 // 修改
+fn inner_upload(args: Vec<UploadingArg>) -> Result<(), StorageError> {
+    let caller = caller();
+    let arg_content = format!(
+        "upload file: [{}]",
+        args.iter()
+            .map(|arg| format!("path: {} size: {} index: {}", arg.path, arg.size, arg.index))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ); // * 记录参数内容
+
+    let paths = args.iter().map(|arg| arg.path.clone()).collect::<Vec<_>>();
+
+    let result = with_mut_state(
+        |s, _done| s.business_upload(args),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    );
+
+    digest_uploads(); // * 数据较多时在后台继续计算 hash
+    commit_assets(); // * 上传完成的数据写入稳定内存
+    update_certified_assets(&paths); // * 更新证书
+
+    result
+}
 #[ic_cdk::update(guard = "has_business_upload")]
 fn business_upload(args: Vec<UploadingArg>) {
     let _guard = call_once_guard(); // post 接口应该拦截
+    trap(check_business_upload_paths(
+        &args.iter().map(|arg| arg.path.clone()).collect::<Vec<_>>(),
+    )); // 旧接口也要检查路径的访问控制
+    trap(inner_upload(args))
+}
+#[ic_cdk::update]
+fn business_upload_v2(args: Vec<UploadingArg>) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(&args.iter().map(|arg| arg.path.clone()).collect::<Vec<_>>())?;
+    inner_upload(args)
+}
 
+// 上传会话
+fn inner_upload_begin(arg: UploadBeginArg) -> Result<u64, StorageError> {
     let caller = caller();
     let arg_content = format!(
-        "upload file: [{}]",
+        "upload begin: path: {} size: {} chunk size: {}",
+        arg.path, arg.size, arg.chunk_size
+    ); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_upload_begin(arg),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    )
+}
+#[ic_cdk::update(guard = "has_business_upload")]
+fn business_upload_begin(arg: UploadBeginArg) -> u64 {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    trap(check_business_upload_paths(std::slice::from_ref(&arg.path)));
+    trap(inner_upload_begin(arg))
+}
+#[ic_cdk::update]
+fn business_upload_begin_v2(arg: UploadBeginArg) -> Result<u64, StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(std::slice::from_ref(&arg.path))?;
+    inner_upload_begin(arg)
+}
+
+fn inner_upload_put(args: Vec<UploadChunkArg>) -> Result<(), StorageError> {
+    let caller = caller();
+    let arg_content = format!(
+        "upload put: [{}]",
         args.iter()
-            .map(|arg| format!("path: {} size: {} index: {}", arg.path, arg.size, arg.index))
+            .map(|arg| format!("session: {} index: {}", arg.session, arg.index))
             .collect::<Vec<_>>()
             .join(", ")
     ); // * 记录参数内容
 
     with_mut_state(
-        |s, _done| {
-            s.business_upload(args);
+        |s, _done| s.business_upload_put(args, caller),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    )
+}
+#[ic_cdk::update(guard = "has_business_upload")]
+fn business_upload_put(args: Vec<UploadChunkArg>) {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    trap(check_business_upload_paths(&session_paths(
+        args.iter().map(|arg| arg.session),
+    )));
+    trap(inner_upload_put(args))
+}
+#[ic_cdk::update]
+fn business_upload_put_v2(args: Vec<UploadChunkArg>) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(&session_paths(args.iter().map(|arg| arg.session)))?;
+    inner_upload_put(args)
+}
+
+#[ic_cdk::query(guard = "has_business_upload")]
+fn business_upload_status(session: u64) -> Option<UploadingStatus> {
+    trap(check_business_upload_paths(&session_paths([session])));
+    with_state(|s| s.business_upload_status(session))
+}
+#[ic_cdk::query]
+fn business_upload_status_v2(session: u64) -> Result<Option<UploadingStatus>, StorageError> {
+    check_business_upload_paths(&session_paths([session]))?;
+    Ok(with_state(|s| s.business_upload_status(session)))
+}
+
+fn inner_upload_commit(session: u64) -> Result<UploadCommitted, StorageError> {
+    let caller = caller();
+    let arg_content = format!("upload commit: session: {session}"); // * 记录参数内容
+
+    let committed = with_mut_state(
+        |s, _done| s.business_upload_commit(session, caller),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    )?;
+
+    match &committed {
+        UploadCommitted::Committed(path) => {
+            commit_assets(); // * 上传完成的数据写入稳定内存
+            update_certified_assets(std::slice::from_ref(path)); // * 更新证书
+        }
+        UploadCommitted::Pending(_) => digest_uploads(), // * 数据较多时在后台继续计算 hash, 完成后再写入
+    }
+
+    Ok(committed)
+}
+#[ic_cdk::update(guard = "has_business_upload")]
+fn business_upload_commit(session: u64) -> String {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    trap(check_business_upload_paths(&session_paths([session])));
+    match trap(inner_upload_commit(session)) {
+        UploadCommitted::Committed(path) | UploadCommitted::Pending(path) => path,
+    }
+}
+#[ic_cdk::update]
+fn business_upload_commit_v2(session: u64) -> Result<UploadCommitted, StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(&session_paths([session]))?;
+    inner_upload_commit(session)
+}
+
+fn inner_upload_abort(session: u64) -> Result<(), StorageError> {
+    let caller = caller();
+    let arg_content = format!("upload abort: session: {session}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_upload_abort(session, caller),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    )
+}
+#[ic_cdk::update(guard = "has_business_upload")]
+fn business_upload_abort(session: u64) {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    trap(check_business_upload_paths(&session_paths([session])));
+    trap(inner_upload_abort(session))
+}
+#[ic_cdk::update]
+fn business_upload_abort_v2(session: u64) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(&session_paths([session]))?;
+    inner_upload_abort(session)
+}
+
+// 上传会话对应的路径, 不存在的会话忽略
+fn session_paths(sessions: impl IntoIterator<Item = u64>) -> Vec<String> {
+    with_state(|s| {
+        sessions
+            .into_iter()
+            .filter_map(|session| s.business_upload_status(session))
+            .map(|status| status.path)
+            .collect()
+    })
+}
+
+// 持有证明, 已经存在的数据不必再上传
+async fn inner_upload_challenge(hash: HashDigest) -> Result<Option<UploadChallenge>, StorageError> {
+    let random = ic_canister_kit::number::random::random().await; // 挑战必须不可预测
+    let random = random.map_err(|err| StorageError::Internal(err.to_string()))?;
+
+    let _guard = call_once_guard(); // post 接口应该拦截, 不能跨越 await
+
+    let caller = caller();
+    let arg_content = format!("upload challenge: hash: {}", hash.hex()); // * 记录参数内容
+
+    Ok(with_mut_state(
+        |s, _done| s.business_upload_challenge(hash, random),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    ))
+}
+#[ic_cdk::update(guard = "has_business_upload")]
+async fn business_upload_challenge(hash: HashDigest) -> Option<UploadChallenge> {
+    trap(inner_upload_challenge(hash).await)
+}
+#[ic_cdk::update]
+async fn business_upload_challenge_v2(hash: HashDigest) -> Result<Option<UploadChallenge>, StorageError> {
+    check_business_upload()?;
+    inner_upload_challenge(hash).await
+}
+
+fn inner_upload_prove(arg: UploadProofArg) -> Result<(), StorageError> {
+    let caller = caller();
+    let arg_content = format!("upload prove: path: {} nonce: {}", arg.path, arg.nonce.hex()); // * 记录参数内容
+
+    let path = arg.path.clone();
+
+    with_mut_state(
+        |s, _done| s.business_upload_prove(arg, ic_canister_kit::times::now(), caller),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    )?;
+
+    update_certified_assets(&[path]); // * 更新证书
+
+    Ok(())
+}
+#[ic_cdk::update(guard = "has_business_upload")]
+fn business_upload_prove(arg: UploadProofArg) {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    trap(check_business_upload_paths(std::slice::from_ref(&arg.path)));
+    trap(inner_upload_prove(arg))
+}
+#[ic_cdk::update]
+fn business_upload_prove_v2(arg: UploadProofArg) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(std::slice::from_ref(&arg.path))?;
+    inner_upload_prove(arg)
+}
+
+// 上传凭证, 后端签发给终端用户, 持有凭证就可以在前缀下上传一个文件, 不需要上传权限
+#[ic_cdk::update]
+async fn business_upload_token_mint(arg: UploadTokenArg) -> Result<UploadToken, StorageError> {
+    check_business_upload_paths(std::slice::from_ref(&arg.prefix))?;
+
+    let random = ic_canister_kit::number::random::random().await; // 凭证必须不可预测
+    let random = random.map_err(|err| StorageError::Internal(err.to_string()))?;
+
+    let _guard = call_once_guard(); // post 接口应该拦截, 不能跨越 await
+
+    let caller = caller();
+    let arg_content = format!(
+        "mint upload token: prefix: {} max size: {} expires at: {}",
+        arg.prefix,
+        arg.max_size,
+        arg.expires_at.into_inner()
+    ); // * 记录参数内容
+
+    let now = ic_canister_kit::times::now();
+    with_mut_state(
+        |s, done| {
+            let result = s.business_upload_token_mint(arg, random, now, caller);
+            *done = Some(match &result {
+                Ok(token) => format!("token id: {}", token.id), // ! 记录中不能出现凭证
+                Err(err) => err.to_string(),
+            });
+            result
         },
         caller,
         RecordTopics::UploadFile.topic(),
@@ -77,19 +442,361 @@ fn business_upload(args: Vec<UploadingArg>) {
     )
 }
 
-#[ic_cdk::update(guard = "has_business_delete")]
-fn business_delete(names: Vec<String>) {
+#[ic_cdk::query]
+fn business_upload_tokens() -> Result<Vec<QueryUploadToken>, StorageError> {
+    check_business_upload()?;
+    Ok(with_state(|s| s.business_upload_tokens()))
+}
+
+// 撤销还没有使用完的上传凭证
+#[ic_cdk::update]
+fn business_upload_token_revoke(id: u64) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload()?;
+
+    let caller = caller();
+    let arg_content = format!("revoke upload token: {id}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_upload_token_revoke(id),
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    )
+}
+
+// 使用上传凭证上传, 代替上传权限, 上传的文件记在签发者名下
+#[ic_cdk::update]
+fn business_upload_by_token(token: String, args: Vec<UploadingArg>) -> Result<(), StorageError> {
     let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_running()?;
 
+    inner_upload_by_token(token, args)
+}
+// http 上传也使用上传凭证
+pub(crate) fn inner_upload_by_token(token: String, args: Vec<UploadingArg>) -> Result<(), StorageError> {
+    let caller = caller();
+    let arg_content = format!(
+        "upload file by token: [{}]",
+        args.iter()
+            .map(|arg| format!("path: {} size: {} index: {}", arg.path, arg.size, arg.index))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ); // * 记录参数内容
+
+    let paths = args.iter().map(|arg| arg.path.clone()).collect::<Vec<_>>();
+
+    let now = ic_canister_kit::times::now();
+    let result = with_mut_state(
+        |s, done| {
+            let result = s.business_upload_by_token(&token, args, now);
+            *done = Some(match &result {
+                Ok(id) => format!("token id: {id}"),
+                Err(err) => err.to_string(),
+            });
+            result
+        },
+        caller,
+        RecordTopics::UploadFile.topic(),
+        arg_content,
+    );
+
+    digest_uploads(); // * 数据较多时在后台继续计算 hash
+    commit_assets(); // * 上传完成的数据写入稳定内存
+    update_certified_assets(&paths); // * 更新证书
+
+    result.map(|_| ())
+}
+
+fn inner_delete(names: Vec<String>) {
     let caller = caller();
     let arg_content = format!("delete file: [{}]", &names.join(", ")); // * 记录参数内容
 
+    let paths = names.clone();
+
     with_mut_state(
         |s, _done| {
-            s.business_delete(names);
+            s.business_delete(names, ic_canister_kit::times::now(), caller);
         },
         caller,
         RecordTopics::DeleteFile.topic(),
         arg_content,
+    );
+
+    update_certified_assets(&paths); // * 更新证书
+}
+#[ic_cdk::update(guard = "has_business_delete")]
+fn business_delete(names: Vec<String>) {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    trap(check_business_delete_paths(&names));
+    inner_delete(names)
+}
+#[ic_cdk::update]
+fn business_delete_v2(names: Vec<String>) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_delete_paths(&names)?;
+    inner_delete(names);
+    Ok(())
+}
+
+// 移动文件, 以 / 结尾的路径表示目录, 不需要重新上传数据
+#[ic_cdk::update]
+fn business_move(from: String, to: String, overwrite: bool) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_read(&from)?; // 移动后的内容可能被更多的人访问
+    check_business_upload_paths(std::slice::from_ref(&to))?;
+    check_business_delete_paths(std::slice::from_ref(&from))?;
+
+    let caller = caller();
+    let arg_content = format!("move file: {from} -> {to} overwrite: {overwrite}"); // * 记录参数内容
+
+    let changed = with_mut_state(
+        |s, _done| s.business_move(from, to, overwrite, ic_canister_kit::times::now()),
+        caller,
+        RecordTopics::MoveFile.topic(),
+        arg_content,
+    )?;
+
+    update_certified_assets(&changed); // * 更新证书
+
+    Ok(())
+}
+
+// 复制文件, 以 / 结尾的路径表示目录, 不覆盖已有的文件
+#[ic_cdk::update]
+fn business_copy(from: String, to: String) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_read(&from)?; // 复制后的内容可能被更多的人访问
+    check_business_upload_paths(std::slice::from_ref(&to))?;
+
+    let caller = caller();
+    let arg_content = format!("copy file: {from} -> {to}"); // * 记录参数内容
+
+    let changed = with_mut_state(
+        |s, _done| s.business_copy(from, to, caller),
+        caller,
+        RecordTopics::CopyFile.topic(),
+        arg_content,
+    )?;
+
+    update_certified_assets(&changed); // * 更新证书
+
+    Ok(())
+}
+
+// 更新文件的 headers, 不需要重新上传数据, 以 / 结尾的路径表示目录下的所有文件
+#[ic_cdk::update]
+fn business_update_headers(path: String, headers: Vec<(String, String)>) -> Result<u64, StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(std::slice::from_ref(&path))?;
+
+    let caller = caller();
+    let arg_content = format!("update headers: {path} headers: {headers:?}"); // * 记录参数内容
+
+    let changed = with_mut_state(
+        |s, _done| s.business_update_headers(path, headers, ic_canister_kit::times::now()),
+        caller,
+        RecordTopics::UpdateHeaders.topic(),
+        arg_content,
+    )?;
+
+    update_certified_assets(&changed); // * headers 也在证书中
+
+    Ok(changed.len() as u64)
+}
+
+// 恢复历史版本, 当前的内容也会保存为历史版本
+#[ic_cdk::update]
+fn business_restore_version(path: String, version: u64) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_upload_paths(std::slice::from_ref(&path))?;
+
+    let caller = caller();
+    let arg_content = format!("restore version: {path} version: {version}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_restore_version(path.clone(), version, ic_canister_kit::times::now(), caller),
+        caller,
+        RecordTopics::RestoreVersion.topic(),
+        arg_content,
+    )?;
+
+    update_certified_assets(&[path]); // * 更新证书
+
+    Ok(())
+}
+
+// 设置历史版本的保留策略, 超出的历史版本由定时任务清除
+#[ic_cdk::update]
+fn business_version_retention_update(retention: VersionRetention) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_delete()?;
+
+    let caller = caller();
+    let arg_content = format!("set version retention: {retention:?}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_version_retention_update(retention),
+        caller,
+        RecordTopics::ExpireVersion.topic(),
+        arg_content,
     )
 }
+
+// 存储用量和配额, 默认查询自己, 查询他人需要权限
+#[ic_cdk::query]
+fn business_usage_query(user: Option<UserId>) -> Result<QueryUsage, StorageError> {
+    let caller = caller();
+    match user {
+        Some(user) if user != caller => check_business_permission(ACTION_PERMISSION_FIND, false)?,
+        _ => check_business_query()?,
+    }
+    Ok(with_state(|s| s.business_usage_query(user.unwrap_or(caller))))
+}
+
+// 设置用户或角色的存储配额, 不指定配额时移除, 用户自己的配额优先于角色
+#[ic_cdk::update]
+fn business_quota_update(target: QuotaTarget, quota: Option<StorageQuota>) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_permission(ACTION_PERMISSION_UPDATE, true)?;
+
+    let caller = caller();
+    let arg_content = format!("update quota: {target:?} quota: {quota:?}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_quota_update(target, quota),
+        caller,
+        RecordTopics::UpdateQuota.topic(),
+        arg_content,
+    )
+}
+
+// 删除目录, 不递归时只删除目录下的直接文件, 文件较多时在后续的消息中继续删除
+#[ic_cdk::update]
+fn business_delete_dir(path: String, recursive: bool) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_delete_paths(&[format!("{}/", path.trim_end_matches('/'))])?; // 目录下的规则都要允许
+
+    let caller = caller();
+    let arg_content = format!("delete dir: {path} recursive: {recursive}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_delete_dir(path, recursive, caller),
+        caller,
+        RecordTopics::DeleteFile.topic(),
+        arg_content,
+    )?;
+
+    delete_dirs(); // * 先删除一批, 同时更新证书
+
+    Ok(())
+}
+
+// 回收站, 最近删除的在前
+#[ic_cdk::query]
+fn business_trash_list(page: QueryPage) -> Result<PageData<QueryTrashedFile>, StorageError> {
+    check_business_query()?;
+    with_state(|s| s.business_trash_list(&page, &caller()))
+}
+
+// 恢复回收站中的文件, 默认恢复到原来的路径
+#[ic_cdk::update]
+fn business_trash_restore(id: u64, to: Option<String>) -> Result<String, StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_delete()?;
+    let target = to.clone().or_else(|| with_state(|s| s.business_trash_path(id)));
+    check_business_upload_paths(&target.into_iter().collect::<Vec<_>>())?; // 恢复相当于在目标路径上传
+
+    let caller = caller();
+    let arg_content = format!("restore trash: {id} to: {to:?}"); // * 记录参数内容
+
+    let path = with_mut_state(
+        |s, _done| s.business_trash_restore(id, to, caller),
+        caller,
+        RecordTopics::DeleteFile.topic(),
+        arg_content,
+    )?;
+
+    update_certified_assets(std::slice::from_ref(&path)); // * 更新证书
+
+    Ok(path)
+}
+
+// 彻底清除回收站中的文件, 不指定时清空整个回收站
+#[ic_cdk::update]
+fn business_trash_empty(ids: Option<Vec<u64>>) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_delete()?;
+
+    let caller = caller();
+    let arg_content = format!("empty trash: {ids:?}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_trash_empty(ids),
+        caller,
+        RecordTopics::DeleteFile.topic(),
+        arg_content,
+    )?;
+
+    empty_trash(); // * 先清除一批, 剩余的在后续的消息中继续清除
+
+    Ok(())
+}
+
+// 清除稳定内存中没有被引用的数据块, 返回第一批删除的块数, 剩余的在后续的消息中继续清除
+fn inner_purge_orphans() -> u64 {
+    let caller = caller();
+    let arg_content = "purge orphan chunks".to_string(); // * 记录参数内容
+
+    let purged = with_mut_state(
+        |s, _done| s.business_purge_orphans(),
+        caller,
+        RecordTopics::DeleteFile.topic(),
+        arg_content,
+    );
+
+    if with_state(|s| s.business_purging()) {
+        ic_canister_kit::functions::schedule::async_execute(async { purge_orphans() });
+    }
+
+    purged
+}
+#[ic_cdk::update(guard = "has_business_delete")]
+fn business_purge_orphans() -> u64 {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    inner_purge_orphans()
+}
+#[ic_cdk::update]
+fn business_purge_orphans_v2() -> Result<u64, StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_delete()?;
+    Ok(inner_purge_orphans())
+}
+
+// 路径的访问控制, 按最长匹配的前缀生效
+#[ic_cdk::query]
+fn permission_acl_find() -> Result<Vec<(String, PathAcl)>, StorageError> {
+    check_business_permission(ACTION_PERMISSION_FIND, false)?;
+    Ok(with_state(|s| s.business_acl_list()))
+}
+
+// 设置路径前缀的访问控制, 不指定规则时移除
+#[ic_cdk::update]
+fn permission_acl_update(prefix: String, acl: Option<PathAcl>) -> Result<(), StorageError> {
+    let _guard = call_once_guard(); // post 接口应该拦截
+    check_business_permission(ACTION_PERMISSION_UPDATE, true)?;
+
+    let caller = caller();
+    let arg_content = format!("update acl: {prefix} acl: {acl:?}"); // * 记录参数内容
+
+    with_mut_state(
+        |s, _done| s.business_acl_update(prefix, acl),
+        caller,
+        RecordTopics::Permission.topic(),
+        arg_content,
+    )?;
+
+    crate::certification::init_certified_assets(); // * 公开的文件才有证书, 规则变化后重新生成
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use ic_canister_kit::types::TimestampNanos;
+use ic_cdk_timers::TimerId;
+use ic_http_certification::utils::{build_v2_certificate_header_value, cbor_encode_to_base64};
+use ic_http_certification::{
+    CERTIFICATE_EXPRESSION_HEADER_NAME, CERTIFICATE_HEADER_NAME, DefaultCelBuilder, DefaultResponseCertification,
+    DefaultResponseOnlyCelExpression, HttpCertification, HttpCertificationPath, HttpCertificationTree,
+    HttpCertificationTreeEntry, HttpResponse, StatusCode,
+};
+
+use crate::stable::{Business, State, with_state};
+use crate::types::{ACTION_BUSINESS_QUERY, AssetFile, AssetStatus};
+
+// https://internetcomputer.org/docs/building-apps/network-features/using-http/http-certification/custom-http-canisters
+
+thread_local! {
+    static TREE: RefCell<HttpCertificationTree> = RefCell::default(); // 证书树 不需要持久化, 升级后重新生成
+    static CERTIFIED: RefCell<HashSet<String>> = RefCell::default(); // 有证书的路径, 这些路径的响应不能跳过验证
+    static EXPIRING: RefCell<HashMap<String, (TimestampNanos, TimerId)>> = RefCell::default(); // 每个路径最多一个过期定时器
+}
+
+// 主页和找不到文件等动态内容, 跳过验证
+fn fallback_entry<'a>() -> HttpCertificationTreeEntry<'a> {
+    HttpCertificationTreeEntry::new(HttpCertificationPath::wildcard(""), HttpCertification::skip())
+}
+
+// 网关匿名访问时直接返回完整内容的文件才有证书, 私有的和过期的文件跳过验证
+fn certifiable(state: &State, file: &AssetFile, now: TimestampNanos) -> bool {
+    file.status == AssetStatus::Committed // 数据还没有写完, 暂不可访问
+        && !file.expired(now)
+        && state.business_path_permitted(&file.path, &candid::Principal::anonymous(), ACTION_BUSINESS_QUERY)
+}
+
+// 文件的验证表达式, 验证状态码, 内容以及文件自身设置的请求头
+fn file_cel_expr(file: &AssetFile) -> DefaultResponseOnlyCelExpression<'_> {
+    let names = file.headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    DefaultCelBuilder::response_only_certification()
+        .with_response_certification(DefaultResponseCertification::certified_response_headers(names))
+        .build()
+}
+
+// 文件的证书 完整内容的 hash 就是文件的 hash, 流式响应也是验证完整的内容; 缓存未过期的 304 没有内容
+fn file_entry(file: &AssetFile, status: StatusCode) -> Option<HttpCertificationTreeEntry<'_>> {
+    let cel_expr = file_cel_expr(file);
+    let mut headers = file.headers.clone();
+    headers.push((CERTIFICATE_EXPRESSION_HEADER_NAME.to_string(), cel_expr.to_string()));
+    let response = HttpResponse::builder()
+        .with_status_code(status)
+        .with_headers(headers)
+        .build();
+    let body_hash = (status == StatusCode::OK).then(|| file.hash.digest());
+    let certification = HttpCertification::response_only(&cel_expr, &response, body_hash).ok()?;
+    Some(HttpCertificationTreeEntry::new(
+        HttpCertificationPath::exact(file.path.as_str()),
+        certification,
+    ))
+}
+
+// 有证书的响应
+const CERTIFIED_STATUS: [StatusCode; 2] = [StatusCode::OK, StatusCode::NOT_MODIFIED];
+
+// 重新生成路径的证书
+fn certify_file(tree: &mut HttpCertificationTree, path: &str, file: Option<&AssetFile>) {
+    tree.delete_by_path(&HttpCertificationPath::exact(path));
+    CERTIFIED.with_borrow_mut(|certified| certified.remove(path));
+    let Some(file) = file else {
+        expire_at(path, None);
+        return;
+    };
+    let entries = CERTIFIED_STATUS
+        .into_iter()
+        .map(|status| file_entry(file, status))
+        .collect::<Option<Vec<_>>>();
+    if let Some(entries) = entries {
+        for entry in entries.iter() {
+            tree.insert(entry);
+        }
+        CERTIFIED.with_borrow_mut(|certified| certified.insert(path.to_string()));
+        expire_at(path, file.expires_at); // 过期时立即移除证书
+    } else {
+        expire_at(path, None);
+    }
+}
+
+// 文件过期时移除证书, 否则过期后到定时任务清除之前都无法通过验证
+// 重新生成证书时过期时间没有变化则沿用原来的定时器
+fn expire_at(path: &str, expires_at: Option<TimestampNanos>) {
+    let exist = EXPIRING.with_borrow_mut(|expiring| expiring.remove(path));
+    if let Some((exist_at, timer)) = exist {
+        if Some(exist_at) == expires_at {
+            EXPIRING.with_borrow_mut(|expiring| expiring.insert(path.to_string(), (exist_at, timer)));
+            return;
+        }
+        ic_cdk_timers::clear_timer(timer);
+    }
+    let Some(expires_at) = expires_at else {
+        return;
+    };
+    let delay = (expires_at.into_inner() - ic_canister_kit::times::now().into_inner()).max(0) as u64;
+    let expired = path.to_string();
+    let timer = ic_cdk_timers::set_timer(std::time::Duration::from_nanos(delay), async move {
+        EXPIRING.with_borrow_mut(|expiring| expiring.remove(&expired));
+        update_certified_assets(&[expired])
+    });
+    EXPIRING.with_borrow_mut(|expiring| expiring.insert(path.to_string(), (expires_at, timer)));
+}
+
+// 每条消息最多生成证书的文件数量
+const MAX_CERTIFY_FILES: usize = 1000;
+
+/// 重新生成所有文件的证书
+/// ! 文件很多时分批在后续的消息中生成, 升级的成本不随文件数量增长
+pub fn init_certified_assets() {
+    TREE.with_borrow_mut(|tree| {
+        tree.clear();
+        tree.insert(&fallback_entry());
+        ic_cdk::api::certified_data_set(tree.root_hash());
+    });
+    CERTIFIED.with_borrow_mut(|certified| certified.clear());
+    certify_assets_after(None);
+}
+
+// 生成指定路径之后的文件的证书
+fn certify_assets_after(path: Option<String>) {
+    let now = ic_canister_kit::times::now();
+    let files = with_state(|s| {
+        s.business_assets_files_after(path, MAX_CERTIFY_FILES)
+            .into_iter()
+            .map(|file| {
+                let certifiable = certifiable(s, &file, now);
+                (file, certifiable)
+            })
+            .collect::<Vec<_>>()
+    });
+    TREE.with_borrow_mut(|tree| {
+        for (file, certifiable) in files.iter() {
+            if *certifiable {
+                certify_file(tree, &file.path, Some(file));
+            }
+        }
+        ic_cdk::api::certified_data_set(tree.root_hash());
+    });
+
+    if files.len() == MAX_CERTIFY_FILES
+        && let Some((file, _)) = files.last()
+    {
+        let path = file.path.clone();
+        ic_canister_kit::functions::schedule::async_execute(async move { certify_assets_after(Some(path)) });
+    }
+}
+
+/// 更新指定路径的证书, 文件不存在或者不能直接访问则删除
+pub fn update_certified_assets(paths: &[String]) {
+    let now = ic_canister_kit::times::now();
+    with_state(|s| {
+        TREE.with_borrow_mut(|tree| {
+            for path in paths {
+                let file = s
+                    .business_assets_get_file(path)
+                    .filter(|file| certifiable(s, file, now));
+                certify_file(tree, path, file.as_ref());
+            }
+            ic_cdk::api::certified_data_set(tree.root_hash());
+        })
+    })
+}
+
+/// 路径是否有证书, 有证书的路径只能返回有证书的响应
+pub fn is_certified(path: &str) -> bool {
+    CERTIFIED.with_borrow(|certified| certified.contains(path))
+}
+
+/// 响应需要携带的证书请求头, 没有文件表示跳过验证
+/// ! 只有 query 调用才能取得证书
+pub fn certificate_headers(path: &str, file: Option<&AssetFile>, status: u16) -> Vec<(&'static str, String)> {
+    let certificate = match ic_cdk::api::data_certificate() {
+        Some(certificate) => certificate,
+        None => return vec![],
+    };
+
+    let status = StatusCode::from_u16(status).ok();
+    let (entry, cel_expr): (HttpCertificationTreeEntry<'_>, String) = match file
+        .filter(|_| is_certified(path))
+        .zip(status.filter(|status| CERTIFIED_STATUS.contains(status)))
+        .and_then(|(file, status)| file_entry(file, status).map(|entry| (entry, file)))
+    {
+        Some((entry, file)) => (entry, file_cel_expr(file).to_string()),
+        None if is_certified(path) => return vec![], // 范围请求等其他响应没有证书, 与原来一样直接返回
+        None => (fallback_entry(), DefaultCelBuilder::skip_certification().to_string()),
+    };
+
+    let witness = match TREE.with_borrow(|tree| tree.witness(&entry, path)) {
+        Ok(witness) => witness,
+        Err(_) => return vec![],
+    };
+    let expr_path = cbor_encode_to_base64(&entry.path.to_expr_path());
+
+    vec![
+        (
+            CERTIFICATE_HEADER_NAME,
+            build_v2_certificate_header_value(&certificate, &witness, &expr_path),
+        ),
+        (CERTIFICATE_EXPRESSION_HEADER_NAME, cel_expr),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_certify_file_by_path() {
+        let file = AssetFile {
+            path: "/images/a.png".into(),
+            created: 0.into(),
+            modified: 0.into(),
+            headers: vec![("Content-Type".into(), "image/png".into())],
+            hash: Default::default(),
+            size: 1,
+            status: AssetStatus::Committed,
+            uploader: None,
+            expires_at: None,
+        };
+        let entry = file_entry(&file, StatusCode::OK);
+        let not_modified = file_entry(&file, StatusCode::NOT_MODIFIED);
+        assert!(entry.is_some() && not_modified.is_some());
+
+        let mut tree = HttpCertificationTree::default();
+        tree.insert(&fallback_entry());
+        let root = tree.root_hash();
+        if let (Some(entry), Some(not_modified)) = (entry, not_modified) {
+            tree.insert(&entry);
+            let certified = tree.root_hash();
+            assert_ne!(certified, root);
+            tree.insert(&not_modified);
+            assert_ne!(tree.root_hash(), certified); // 304 是另一个证书
+            assert!(tree.witness(&entry, &file.path).is_ok());
+            assert!(tree.witness(&not_modified, &file.path).is_ok());
+            tree.delete_by_path(&entry.path);
+        }
+        assert_eq!(tree.root_hash(), root);
+        assert!(tree.witness(&fallback_entry(), "/not/found").is_ok());
+    }
+}
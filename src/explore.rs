@@ -1,14 +1,14 @@
 use std::{borrow::Cow, collections::HashMap};
 
-use crate::stable::{Business, State};
+use crate::stable::{Business, State, UserId};
 
 pub const HTML: &str = include_str!("../web/index.html");
 pub const CSS: &str = include_str!("../web/index.css");
 
-pub fn explore<'a>(headers: &mut HashMap<&'a str, Cow<'a, str>>, state: &State) -> Vec<u8> {
+pub fn explore<'a>(headers: &mut HashMap<&'a str, Cow<'a, str>>, state: &State, caller: &UserId) -> Vec<u8> {
     headers.insert("Content-Type", "text/html".into());
 
-    let files = state.business_files();
+    let files = state.business_files(caller); // 只展示可以访问的文件
     let mut json = String::from("");
     json.push('[');
     json.push_str(
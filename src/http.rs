@@ -5,25 +5,71 @@ use regex::Regex;
 
 use ic_canister_kit::http::MAX_RESPONSE_LENGTH;
 
+use crate::certification::certificate_headers;
 use crate::explore::explore;
 use crate::stable::State;
 use crate::types::*;
 
 // https://github.com/dfinity/examples/blob/8b01d548d8548a9d4558a7a1dbb49234d02d7d03/motoko/http_counter/src/main.mo
 
-// #[ic_cdk::update]
-// fn http_request_update(request: CustomHttpRequest) -> CustomHttpResponse {
-//     todo!()
-// }
+// 上传数据, 网关的调用都是匿名的, 使用上传凭证代替上传权限
+#[ic_cdk::update]
+fn http_request_update(request: CustomHttpRequest) -> CustomHttpResponse {
+    let _guard = ic_canister_kit::common::once::call_once_guard(); // post 接口应该拦截
+
+    let mut code = 201;
+    let mut headers: HashMap<&str, Cow<str>> = HashMap::new();
+    headers.insert("Access-Control-Allow-Origin", "*".into()); // 网页可以读取上传的结果
+
+    let body = if !is_upload_method(&request.method) {
+        method_not_allowed(&mut code, &mut headers) // 只有上传会升级为 update 调用
+    } else {
+        headers.insert("Content-Type", "text/plain".into());
+        match check_business_running().and_then(|_| http_upload(request)) {
+            Ok(path) => format!("Created: {path}").into_bytes(),
+            Err(err) => {
+                code = upload_error_code(&err);
+                if code == 401 {
+                    headers.insert("WWW-Authenticate", "Bearer".into());
+                }
+                err.to_string().into_bytes()
+            }
+        }
+    };
+
+    CustomHttpResponse {
+        status_code: code,
+        headers: headers
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        body,
+        streaming_strategy: None,
+        upgrade: None,
+    }
+}
 
 // 请求数据
 #[ic_cdk::query]
 fn http_request(request: CustomHttpRequest) -> CustomHttpResponse {
-    crate::stable::with_state(|state| inner_http_request(state, request))
+    let caller = ic_canister_kit::identity::caller();
+    crate::stable::with_state(|state| inner_http_request(state, request, &caller))
 }
 
 #[inline]
-fn inner_http_request(state: &State, req: CustomHttpRequest) -> CustomHttpResponse {
+fn inner_http_request(state: &State, req: CustomHttpRequest, caller: &UserId) -> CustomHttpResponse {
+    // 上传需要升级为 update 调用
+    if is_upload_method(&req.method) {
+        return CustomHttpResponse {
+            status_code: 200,
+            headers: HashMap::new(),
+            body: vec![],
+            streaming_strategy: None,
+            upgrade: Some(true),
+        };
+    }
+
+    let head = req.method.eq_ignore_ascii_case("HEAD"); // 只返回响应头
     let mut split_url = req.url.split('?');
     let request_headers = req.headers;
 
@@ -39,23 +85,54 @@ fn inner_http_request(state: &State, req: CustomHttpRequest) -> CustomHttpRespon
     //     ic_cdk::println!("header: {}: {}", key, value);
     // }
 
+    let version = request_version(&params); // 指定了历史版本
+    let now = ic_canister_kit::times::now();
+    let file = match (path.as_ref(), version) {
+        ("/", _) => None,
+        (path, None) => state.business_assets_get_file(path), // 根据路径找文件
+        (path, Some(version)) => version.and_then(|version| state.business_assets_get_version(path, version)),
+    }
+    .filter(|file| file.status == AssetStatus::Committed) // 写入中或者校验失败的文件不可访问
+    .filter(|file| !file.expired(now)); // 过期的文件不可访问
+
     let mut code = 200; // 响应码默认是 200
     let mut headers: HashMap<&str, Cow<str>> = HashMap::new();
     let body: Vec<u8>;
     let mut streaming_strategy: Option<StreamingStrategy> = None;
 
-    if path == "/" {
-        body = explore(&mut headers, state); // 主页内容
+    let mut certified: Option<&AssetFile> = None; // 需要验证的文件
+
+    if req.method.eq_ignore_ascii_case("OPTIONS") {
+        body = preflight(&request_headers, &mut code, &mut headers); // 跨域预检请求
+    } else if !head && !req.method.eq_ignore_ascii_case("GET") {
+        body = method_not_allowed(&mut code, &mut headers); // 不支持的请求方法
+    } else if path == "/" {
+        body = explore(&mut headers, state, caller); // 主页内容
     } else {
-        // 根据路径找文件
-        let file = state.business_assets_get_file(path.as_ref());
-        if let Some(file) = file {
+        if let Some(file) = &file
+            && !state.business_path_permitted(&file.path, caller, ACTION_BUSINESS_QUERY)
+            && !request_signature(&params)
+                .is_some_and(|(sig, exp)| state.business_url_verified(&file.path, version.flatten(), &sig, exp, now))
+        {
+            body = forbidden(&mut code, &mut headers); // 访问控制不允许读取, 也没有有效的签名
+        } else if let Some(file) = &file {
             let asset = state.business_assets_get(&file.hash);
             if let Some(asset) = asset {
-                let (_body, _streaming_strategy): (Vec<u8>, Option<StreamingStrategy>) =
-                    toast(&path, &params, &request_headers, file, asset, &mut code, &mut headers); // 有对应的文件
+                let (_body, _streaming_strategy): (Vec<u8>, Option<StreamingStrategy>) = toast(
+                    &path,
+                    &params,
+                    &request_headers,
+                    file,
+                    &asset,
+                    head,
+                    &mut code,
+                    &mut headers,
+                ); // 有对应的文件
                 body = _body;
                 streaming_strategy = _streaming_strategy;
+                if (code == 200 || code == 304) && version.is_none() && !head {
+                    certified = Some(file); // 只有完整内容和 304 有证书, 范围请求, HEAD 和历史版本都没有
+                }
             } else {
                 body = not_found(&mut code, &mut headers);
             }
@@ -64,6 +141,21 @@ fn inner_http_request(state: &State, req: CustomHttpRequest) -> CustomHttpRespon
         }
     }
 
+    // HEAD 请求不返回内容, 但长度是完整内容的长度
+    let body = if head {
+        let length = body.len().to_string();
+        headers.entry("Content-Length").or_insert(length.into());
+        streaming_strategy = None;
+        vec![]
+    } else {
+        body
+    };
+
+    // 可信验证, 范围请求, HEAD 和历史版本没有证书, 与原来的范围请求一样直接返回
+    for (name, value) in certificate_headers(&path, certified, code) {
+        headers.insert(name, value.into());
+    }
+
     CustomHttpResponse {
         status_code: code,
         headers: headers
@@ -77,35 +169,55 @@ fn inner_http_request(state: &State, req: CustomHttpRequest) -> CustomHttpRespon
 }
 
 #[inline]
+#[allow(clippy::too_many_arguments)] // ? 请求的各个部分分开传入
 fn toast<'a>(
     path: &str,
     params: &str,
     request_headers: &HashMap<String, String>,
     file: &'a AssetFile,
     asset: &AssetData,
+    head: bool,
     code: &mut u16,
     headers: &mut HashMap<&'a str, Cow<'a, str>>,
 ) -> (Vec<u8>, Option<StreamingStrategy>) {
     // 1. 设置 header
-    let (offset, size, streaming_strategy) = set_headers(path, params, request_headers, file, code, headers);
+    let (multipart, offset, size, length, streaming_strategy) =
+        match set_headers(path, params, request_headers, file, MAX_RESPONSE_LENGTH, code, headers) {
+            Some(range) => range,
+            None => return (vec![], None), // 范围无效 没有内容
+        };
+
+    // HEAD 请求不需要读取内容
+    if head {
+        headers.insert("Content-Length", length.to_string().into());
+        return (vec![], None);
+    }
 
     // 2. 返回指定的内容
-    (
-        (asset.slice(&file.hash, file.size, offset, size)).to_vec(),
-        streaming_strategy,
-    )
+    let body = match multipart {
+        Some(multipart) => multipart.read(offset, offset + size, |offset, size| {
+            asset.slice(&file.hash, file.size, offset, size)
+        }),
+        None => (asset.slice(&file.hash, file.size, offset, size)).to_vec(),
+    };
+    (body, streaming_strategy)
 }
 
+// 响应内容的范围: 多个范围, 起始位置, 本次返回的长度, 完整内容的长度, 流式响应
+type ResponseRange = (Option<MultipartRanges>, usize, usize, usize, Option<StreamingStrategy>);
 #[inline]
 fn set_headers<'a>(
     path: &str,
     params: &str,
     request_headers: &HashMap<String, String>,
     file: &'a AssetFile,
+    max_length: usize, // 本次响应的最大长度, 剩余的内容通过流式响应返回
     code: &mut u16,
     headers: &mut HashMap<&'a str, Cow<'a, str>>,
-) -> (usize, usize, Option<StreamingStrategy>) {
+) -> Option<ResponseRange> {
     let size = file.size as usize;
+    let version = request_version(params).flatten(); // 流式响应也要读取相同的版本
+    let signature = request_signature(params); // 流式响应也要带上签名
 
     // let mut gzip = false;
     // let mut content_type = "";
@@ -134,81 +246,86 @@ fn set_headers<'a>(
         }
     }
 
-    // ! 这个时间库无法编译
-    // use chrono::{TimeZone, Utc};
-    // let modified = Utc.timestamp_nanos(file.modified as i64);
-    // headers.insert("Last-Modified", modified.to_rfc2822().into());
-
     // 额外增加的请求头
-    headers.insert("ETag", file.hash.hex().into()); // 缓存标识
+    headers.insert("ETag", format!("\"{}\"", file.etag()).into()); // 缓存标识, 只修改 headers 也会改变
+    headers.insert("Last-Modified", http_date(file.modified).into()); // 修改时间
+    headers.insert("Accept-Ranges", "bytes".into()); // 支持范围请求
 
-    // 访问控制
-    // headers.insert("Access-Control-Allow-Origin", "*".into());
-    // headers.insert(
-    //     "Access-Control-Allow-Methods",
-    //     "HEAD, GET, POST, OPTIONS".into(),
-    // );
-    // headers.insert("Access-Control-Max-Age", "86400".into());
+    // 访问控制, 预检请求见 preflight
+    headers.insert("Access-Control-Allow-Origin", "*".into());
+
+    // 独立的请求头内容
+    for (name, value) in file.headers.iter() {
+        headers.insert(name, value.into());
+    }
+
+    // 缓存未过期 直接返回 304
+    if not_modified(request_headers, file) {
+        *code = 304;
+        return None;
+    }
 
     // Range 设置
-    let mut ranged: bool = false; // 是否 range 请求
+    let range = request_header(request_headers, "range")
+        .map(|v| parse_range(v, size))
+        .unwrap_or(RequestRange::Full);
 
-    // let mut offset: usize = 0; // ! 起始位置 包含 // ? chrome 支持 safari 不支持
-    let offset: usize = 0; // ! 起始位置 包含 // ? chrome 支持 safari 不支持
+    let (ranged, offset, offset_end) = match range {
+        RequestRange::Full => (false, 0, size),
+        RequestRange::Partial(offset, offset_end) if offset < offset_end => (true, offset, offset_end),
+        RequestRange::Multiple(ranges) if ranges.iter().any(|(start, end)| start < end) => {
+            // 多个范围 需要组装成 multipart/byteranges 内容
+            let multipart = MultipartRanges::new(file, ranges);
+            headers.retain(|name, _| !name.eq_ignore_ascii_case("content-type"));
+            headers.insert(
+                "Content-Type",
+                format!("multipart/byteranges; boundary={}", multipart.boundary).into(),
+            );
 
-    // let mut offset_end: usize = size; // ! 末尾位置 不包含 // ? chrome 不支持 safari 不支持
-    let offset_end: usize = size; // ! 末尾位置 不包含 // ? chrome 不支持 safari 不支持
+            let length = multipart.length;
+            let mut streaming_end = length; // ! 末尾位置 不包含
+            let mut streaming_strategy: Option<StreamingStrategy> = None;
+            if max_length < streaming_end {
+                // 组合后的内容太长了, 剩余的内容通过流式响应返回
+                streaming_end = max_length;
+                streaming_strategy = Some(to_streaming_strategy(
+                    path.to_string(),
+                    version,
+                    streaming_end as u64,
+                    length as u64,
+                    Some(multipart.ranges()),
+                    signature,
+                ));
+            }
 
-    if let Some(range) = request_headers
-        .iter()
-        .find(|(key, _)| &key.to_lowercase() == "range")
-        .map(|(_, v)| v.trim())
-    {
-        // https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Range
-        // bytes=start-end
-        if let Some(_range) = range.strip_prefix("bytes=") {
-            ranged = true;
-
-            // ? chrome 支持 safari 不支持
-            // let mut ranges = range.split(',').next().unwrap_or_default().split('-');
-            // let s = ranges.next().map(|s| s.parse().unwrap_or(0)).unwrap_or(0);
-            // if s < size {
-            //     offset = s; // ! 起始位置 包含
-            // }
-
-            // ? chrome 不支持 safari 不支持
-            // let e = ranges
-            //     .next()
-            //     .map(|s| s.parse().unwrap_or(size - 1))
-            //     .unwrap_or(size - 1);
-            // if offset < e && e < size {
-            //     offset_end = e + 1; // ! 末尾位置 不包含
-            // }
+            *code = 206;
+            return Some((Some(multipart), 0, streaming_end, length, streaming_strategy));
         }
-    }
-
-    // 独立的请求头内容
-    for (name, value) in file.headers.iter() {
-        headers.insert(name, value.into());
-    }
+        RequestRange::Partial(..) | RequestRange::Multiple(..) | RequestRange::Unsatisfiable => {
+            *code = 416; // 请求的范围无法满足, 空文件的后缀范围也是空的
+            headers.insert("Content-Range", format!("bytes */{}", size).into());
+            return None;
+        }
+    };
 
     // ic_cdk::println!("---------- {} {} ----------", start, end);
     // 如果过长, 需要阶段显示
     let mut streaming_end = offset_end; // ! 末尾位置 不包含
     let mut streaming_strategy: Option<StreamingStrategy> = None;
-    if offset + MAX_RESPONSE_LENGTH < streaming_end {
+    if offset + max_length < streaming_end {
         // 响应的范围太大了, 缩短为最大长度, 此时应当开启流式响应
-        streaming_end = offset + MAX_RESPONSE_LENGTH; // ! 末尾位置 不包含
+        streaming_end = offset + max_length; // ! 末尾位置 不包含
         streaming_strategy = Some(to_streaming_strategy(
             path.to_string(),
+            version,
             streaming_end as u64,
             offset_end as u64,
+            None,
+            signature,
         ));
     }
 
     if ranged {
-        headers.insert("Accept-Ranges", "bytes".into()); // 支持范围请求
-
         // https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Content-Range
         // Content-Range: bytes 0-499/10000
         headers.insert(
@@ -220,13 +337,455 @@ fn set_headers<'a>(
     // ! 长度设置了会出错
     // headers.insert("Content-Length", format!("{}", offset_end - offset).into()); // ? 这个应该是本次返回的长度
 
-    // 如果是视频可能需要返回其他的
-    *code = 200;
-    if ranged && offset_end < size {
-        *code = 206; // 本次请求并没有返回要求的数据，还有内容没给，因此要返回 206
+    // 范围请求返回 206, 剩余的内容由流式响应补齐
+    *code = if ranged { 206 } else { 200 };
+
+    Some((
+        None,
+        offset,
+        streaming_end - offset,
+        offset_end - offset,
+        streaming_strategy,
+    ))
+}
+
+// 请求参数中的历史版本, 外层表示是否指定, 内层表示版本号是否有效
+#[inline]
+fn request_version(params: &str) -> Option<Option<u64>> {
+    params
+        .split('&')
+        .find_map(|param| param.strip_prefix("version="))
+        .map(|version| version.parse().ok())
+}
+
+// 请求参数中的签名和过期时间(秒)
+type UrlSignature = (String, u64);
+#[inline]
+fn request_signature(params: &str) -> Option<UrlSignature> {
+    let param = |name: &str| params.split('&').find_map(|param| param.strip_prefix(name));
+    let sig = param("sig=")?;
+    let exp = param("exp=")?.parse().ok()?;
+    Some((sig.to_string(), exp))
+}
+
+// 请求头名称不区分大小写
+#[inline]
+fn request_header<'a>(request_headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    request_headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+// https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Last-Modified
+// 格式为 <day-name>, <day> <month> <year> <hour>:<minute>:<second> GMT
+fn http_date(timestamp: TimestampNanos) -> String {
+    chrono::DateTime::from_timestamp_nanos(timestamp.into_inner() as i64)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+// https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Conditional_requests
+// If-None-Match 优先, 存在时忽略 If-Modified-Since
+fn not_modified(request_headers: &HashMap<String, String>, file: &AssetFile) -> bool {
+    if let Some(etags) = request_header(request_headers, "if-none-match") {
+        let current = file.etag();
+        return etags.split(',').map(|etag| etag.trim()).any(|etag| {
+            etag == "*" || etag.strip_prefix("W/").unwrap_or(etag).trim_matches('"') == current // 弱比较
+        });
+    }
+
+    if let Some(since) = request_header(request_headers, "if-modified-since")
+        && let Ok(since) = chrono::DateTime::parse_from_rfc2822(since)
+    {
+        // 时间只精确到秒
+        let modified = (file.modified.into_inner() / 1_000_000_000) as i64;
+        return modified <= since.timestamp();
+    }
+
+    false
+}
+
+// 请求的范围
+#[derive(Debug, PartialEq, Eq)]
+enum RequestRange {
+    Full,                  // 没有有效的 Range 请求，返回全部内容
+    Partial(usize, usize), // 起始位置 包含, 末尾位置 不包含
+    Multiple(ByteRanges),  // 多个范围，返回 multipart/byteranges
+    Unsatisfiable,         // 范围超出文件大小，返回 416
+}
+
+// 多个范围 起始位置 包含, 末尾位置 不包含
+type ByteRanges = Vec<(usize, usize)>;
+
+// 最多支持的范围个数, 过多的小范围请求按照规范忽略
+const MAX_RANGES: usize = 16;
+
+// https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Range
+// 支持 bytes=start-end bytes=start- bytes=-suffix 三种格式，多个范围以逗号分隔
+// 无法解析的 Range 按照规范忽略，返回全部内容
+fn parse_range(range: &str, size: usize) -> RequestRange {
+    let range = match range.trim().strip_prefix("bytes=") {
+        Some(range) => range.trim(),
+        None => return RequestRange::Full, // 不支持的单位
+    };
+
+    let mut ranges = Vec::new();
+    for spec in range.split(',') {
+        match parse_range_spec(spec, size) {
+            Some(Some(range)) => ranges.push(range),
+            Some(None) => {} // 无法满足的范围直接忽略
+            None => return RequestRange::Full,
+        }
+    }
+    if MAX_RANGES < ranges.len() {
+        return RequestRange::Full;
+    }
+
+    match ranges.len() {
+        0 => RequestRange::Unsatisfiable, // 所有的范围都无法满足
+        1 => RequestRange::Partial(ranges[0].0, ranges[0].1),
+        _ => RequestRange::Multiple(ranges),
+    }
+}
+
+// 解析单个范围, None 表示格式错误, Some(None) 表示范围无法满足
+fn parse_range_spec(spec: &str, size: usize) -> Option<Option<(usize, usize)>> {
+    let (start, end) = spec.trim().split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    match (start.is_empty(), end.is_empty()) {
+        // bytes=-suffix 最后 suffix 个字节
+        (true, false) => match end.parse::<usize>().ok()? {
+            0 => Some(None),
+            suffix => Some(Some((size.saturating_sub(suffix), size))),
+        },
+        // bytes=start- 从 start 到末尾
+        (false, true) => {
+            let start = start.parse::<usize>().ok()?;
+            Some((start < size).then_some((start, size)))
+        }
+        // bytes=start-end 末尾位置包含，超出文件大小的部分截断
+        (false, false) => {
+            let start = start.parse::<usize>().ok()?;
+            let end = end.parse::<usize>().ok()?;
+            if end < start {
+                return None;
+            }
+            Some((start < size).then_some((start, std::cmp::min(end + 1, size))))
+        }
+        (true, true) => None,
+    }
+}
+
+// https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Range_requests
+// multipart/byteranges 的响应内容，由分隔文本和文件片段依次组成
+struct MultipartRanges {
+    boundary: String,
+    parts: Vec<MultipartPart>,
+    length: usize, // 整个响应内容的长度
+}
+
+enum MultipartPart {
+    Text(Vec<u8>),      // 分隔符和每一段的请求头
+    Data(usize, usize), // 文件片段 起始位置 包含, 末尾位置 不包含
+}
+
+impl MultipartRanges {
+    fn new(file: &AssetFile, ranges: ByteRanges) -> Self {
+        let boundary = file.hash.hex(); // 流式响应需要能够重新生成相同的内容
+        let content_type = file
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.as_str());
+
+        let ranges = ranges
+            .into_iter()
+            .filter(|(start, end)| start < end) // 空的范围没有内容, 不输出
+            .collect::<Vec<_>>();
+        let mut parts = Vec::with_capacity(ranges.len() * 2 + 1);
+        for (i, (start, end)) in ranges.into_iter().enumerate() {
+            let mut text = String::new();
+            if 0 < i {
+                text.push_str("\r\n");
+            }
+            text.push_str(&format!("--{boundary}\r\n"));
+            if let Some(content_type) = content_type {
+                text.push_str(&format!("Content-Type: {content_type}\r\n"));
+            }
+            text.push_str(&format!(
+                "Content-Range: bytes {}-{}/{}\r\n\r\n",
+                start,
+                end - 1,
+                file.size
+            ));
+            parts.push(MultipartPart::Text(text.into_bytes()));
+            parts.push(MultipartPart::Data(start, end));
+        }
+        parts.push(MultipartPart::Text(format!("\r\n--{boundary}--\r\n").into_bytes()));
+
+        let length = parts
+            .iter()
+            .map(|part| match part {
+                MultipartPart::Text(text) => text.len(),
+                MultipartPart::Data(start, end) => end - start,
+            })
+            .sum();
+
+        Self {
+            boundary,
+            parts,
+            length,
+        }
+    }
+
+    // 流式响应的 token 中记录范围, 格式为 start-end,start-end 末尾位置包含
+    fn ranges(&self) -> String {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                MultipartPart::Data(start, end) => Some(format!("{}-{}", start, end - 1)),
+                MultipartPart::Text(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    // 读取响应内容中的一段 起始位置 包含, 末尾位置 不包含
+    fn read<'a, F>(&self, offset: usize, offset_end: usize, slice: F) -> Vec<u8>
+    where
+        F: Fn(usize, usize) -> Cow<'a, [u8]>, // 读取文件片段 起始位置 和 长度
+    {
+        let mut result = Vec::with_capacity(offset_end.saturating_sub(offset));
+        let mut cursor = 0; // 当前片段在整个响应内容中的起始位置
+        for part in self.parts.iter() {
+            let length = match part {
+                MultipartPart::Text(text) => text.len(),
+                MultipartPart::Data(start, end) => end - start,
+            };
+            let (part_start, part_end) = (cursor, cursor + length);
+            cursor = part_end;
+            if part_end <= offset {
+                continue; // 还没到需要的位置
+            }
+            if offset_end <= part_start {
+                break; // 已经取完了
+            }
+
+            let from = std::cmp::max(offset, part_start) - part_start; // 片段内的起始位置
+            let to = std::cmp::min(offset_end, part_end) - part_start; // 片段内的末尾位置
+            match part {
+                MultipartPart::Text(text) => result.extend_from_slice(&text[from..to]),
+                MultipartPart::Data(start, _) => result.extend_from_slice(&slice(start + from, to - from)),
+            }
+        }
+        result
+    }
+}
+
+// ============================== http 上传 ==============================
+
+// 上传的文件允许保存的请求头, 其他请求头不保存
+const UPLOAD_HEADERS: [&str; 5] = [
+    "content-type",
+    "content-encoding",
+    "content-language",
+    "content-disposition",
+    "cache-control",
+];
+
+// PUT /path 上传请求体, POST multipart/form-data 上传第一个文件
+#[inline]
+fn is_upload_method(method: &str) -> bool {
+    method.eq_ignore_ascii_case("PUT") || method.eq_ignore_ascii_case("POST")
+}
+
+// 上传成功返回文件路径
+fn http_upload(req: CustomHttpRequest) -> Result<String, StorageError> {
+    let mut split_url = req.url.split('?');
+    let path = split_url.next().unwrap_or("/");
+    let path = percent_decode_str(path).decode_utf8().unwrap_or(Cow::Borrowed(path));
+    let params = split_url.next().unwrap_or("");
+    let params = percent_decode_str(params)
+        .decode_utf8()
+        .unwrap_or(Cow::Borrowed(params));
+
+    // 1. 上传凭证, 优先使用 Authorization 请求头, 网页表单可以放在请求参数中
+    let token = request_header(&req.headers, "authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+        .or_else(|| request_token(&params))
+        .ok_or_else(|| StorageError::InvalidToken("missing upload token".into()))?;
+
+    // 2. 解析文件内容
+    let allowed = |name: &str| UPLOAD_HEADERS.iter().any(|allowed| name.eq_ignore_ascii_case(allowed));
+    let (path, headers, chunk) = if req.method.eq_ignore_ascii_case("PUT") {
+        let headers = req
+            .headers
+            .iter()
+            .filter(|(name, _)| allowed(name))
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string()))
+            .collect::<Vec<_>>();
+        (path.to_string(), headers, req.body)
+    } else {
+        let content_type = request_header(&req.headers, "content-type").unwrap_or_default();
+        let file = multipart_file(content_type, &req.body)
+            .ok_or_else(|| StorageError::InvalidArgument("multipart/form-data with a file is required".into()))?;
+        let path = match path.strip_suffix('/') {
+            Some(dir) => format!("{dir}/{}", file.filename), // 上传到目录下, 使用原来的文件名
+            None => path.to_string(),
+        };
+        let headers = file
+            .headers
+            .into_iter()
+            .filter(|(name, _)| allowed(name) && !name.eq_ignore_ascii_case("content-disposition")) // 表单的描述不保存
+            .map(|(name, value)| (name.to_ascii_lowercase(), value))
+            .collect::<Vec<_>>();
+        (path, headers, file.data.to_vec())
+    };
+
+    // 3. 一次上传完整的文件
+    let hash: [u8; 32] = {
+        use sha2::Digest;
+        sha2::Sha256::digest(&chunk).into()
+    };
+    let arg = UploadingArg {
+        path: path.clone(),
+        headers,
+        hash: hash.into(),
+        size: chunk.len() as u64,
+        chunk_size: chunk.len() as u32,
+        index: 0,
+        chunk,
+        expires_at: None,
+    };
+    crate::business::inner_upload_by_token(token, vec![arg])?;
+
+    Ok(path)
+}
+
+// 请求参数中的上传凭证
+#[inline]
+fn request_token(params: &str) -> Option<String> {
+    params
+        .split('&')
+        .find_map(|param| param.strip_prefix("token="))
+        .map(|token| token.to_string())
+}
+
+// 错误对应的响应码
+fn upload_error_code(err: &StorageError) -> u16 {
+    match err {
+        StorageError::InvalidToken(_) => 401,
+        StorageError::PermissionDenied(_) => 403,
+        StorageError::NotFound(_) => 404,
+        StorageError::AlreadyExists(_) | StorageError::Committing(_) => 409,
+        StorageError::TooLarge { .. } => 413,
+        StorageError::QuotaExceeded { .. } => 507,
+        StorageError::Paused(_) => 503,
+        StorageError::Internal(_) => 500,
+        _ => 400,
+    }
+}
+
+// https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Methods/POST
+// multipart/form-data 中的文件
+struct MultipartFile<'a> {
+    filename: String,
+    headers: Vec<(String, String)>,
+    data: &'a [u8],
+}
+
+#[inline]
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// 找到第一个带有文件名的部分
+fn multipart_file<'a>(content_type: &str, body: &'a [u8]) -> Option<MultipartFile<'a>> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    let boundary = params
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    if boundary.is_empty() {
+        return None;
+    }
+
+    let delimiter = format!("--{boundary}");
+    let mut rest = &body[find_bytes(body, delimiter.as_bytes())? + delimiter.len()..];
+    let delimiter = format!("\r\n--{boundary}"); // 除了第一个, 分隔符前面都有换行
+    loop {
+        // 每个部分由请求头, 空行, 内容组成
+        let part = rest.strip_prefix(b"\r\n")?; // 以 -- 结尾说明已经结束了
+        let end = find_bytes(part, delimiter.as_bytes())?;
+        let (part, next) = (&part[..end], &part[end + delimiter.len()..]);
+        rest = next;
+
+        let split = find_bytes(part, b"\r\n\r\n")?;
+        let head = std::str::from_utf8(&part[..split]).ok()?;
+        let headers = head
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect::<Vec<_>>();
+        let filename = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-disposition"))
+            .and_then(|(_, value)| {
+                value
+                    .split(';')
+                    .find_map(|param| param.trim().strip_prefix("filename="))
+            })
+            .map(|filename| filename.trim_matches('"'))
+            .and_then(|filename| filename.rsplit(['/', '\\']).next()) // 只保留文件名
+            .filter(|filename| !filename.is_empty());
+        if let Some(filename) = filename {
+            return Some(MultipartFile {
+                filename: filename.to_string(),
+                headers,
+                data: &part[split + 4..],
+            });
+        }
     }
+}
+
+// 支持的请求方法
+const ALLOWED_METHODS: &str = "GET, HEAD, PUT, POST, OPTIONS";
+
+// https://developer.mozilla.org/zh-CN/docs/Web/HTTP/CORS#预检请求
+// 跨域预检请求, 允许任意来源读取和上传
+#[inline]
+fn preflight<'a>(
+    request_headers: &HashMap<String, String>,
+    code: &mut u16,
+    headers: &mut HashMap<&'a str, Cow<'a, str>>,
+) -> Vec<u8> {
+    *code = 204;
+
+    headers.insert("Allow", ALLOWED_METHODS.into());
+    headers.insert("Access-Control-Allow-Origin", "*".into());
+    headers.insert("Access-Control-Allow-Methods", ALLOWED_METHODS.into());
+    let allowed_headers = request_header(request_headers, "access-control-request-headers")
+        .unwrap_or("Authorization, Content-Type, Range, If-None-Match, If-Modified-Since");
+    headers.insert("Access-Control-Allow-Headers", allowed_headers.to_string().into());
+    headers.insert("Access-Control-Max-Age", "86400".into());
+
+    vec![]
+}
+
+#[inline]
+fn method_not_allowed<'a>(code: &mut u16, headers: &mut HashMap<&'a str, Cow<'a, str>>) -> Vec<u8> {
+    *code = 405;
+
+    headers.insert("Allow", ALLOWED_METHODS.into());
+    headers.insert("Content-Type", "text/plain".into());
 
-    (offset, streaming_end - offset, streaming_strategy)
+    b"Method not allowed"[..].into()
 }
 
 // 找不到对应的文件
@@ -240,33 +799,91 @@ fn not_found<'a>(code: &mut u16, headers: &mut HashMap<&'a str, Cow<'a, str>>) -
 }
 
 #[inline]
-fn to_streaming_strategy(path: String, offset: u64, offset_end: u64) -> StreamingStrategy {
+fn forbidden<'a>(code: &mut u16, headers: &mut HashMap<&'a str, Cow<'a, str>>) -> Vec<u8> {
+    *code = 403;
+
+    headers.insert("Content-Type", "text/plain".into());
+
+    b"Forbidden"[..].into()
+}
+
+#[inline]
+fn to_streaming_strategy(
+    path: String,
+    version: Option<u64>,
+    offset: u64,
+    offset_end: u64,
+    ranges: Option<String>,
+    signature: Option<UrlSignature>,
+) -> StreamingStrategy {
     StreamingStrategy::Callback {
         callback: HttpRequestStreamingCallback::new(ic_cdk::api::canister_self(), "http_streaming".into()),
-        token: to_streaming_token(path, offset, offset_end),
+        token: to_streaming_token(path, version, offset, offset_end, ranges, signature),
     }
 }
 #[inline]
-fn to_streaming_token(path: String, offset: u64, offset_end: u64) -> StreamingCallbackToken {
+fn to_streaming_token(
+    path: String,
+    version: Option<u64>,
+    offset: u64,
+    offset_end: u64,
+    ranges: Option<String>,
+    signature: Option<UrlSignature>,
+) -> StreamingCallbackToken {
     StreamingCallbackToken {
         path,
         token: {
             let mut token = HashMap::new();
+            if let Some(version) = version {
+                token.insert("version".into(), version.to_string()); // ! 历史版本
+            }
             token.insert("start".into(), offset.to_string()); // ! 新的位置 包含
             token.insert("end".into(), offset_end.to_string()); // ! 末尾位置 不包含
+            if let Some(ranges) = ranges {
+                token.insert("ranges".into(), ranges); // ! 多个范围请求, 位置是 multipart 内容中的位置
+            }
+            if let Some((sig, exp)) = signature {
+                token.insert("sig".into(), sig); // ! 签名链接
+                token.insert("exp".into(), exp.to_string());
+            }
             token
         },
     }
 }
+// 流式响应 token 的内容: 路径, 历史版本, 起始位置, 末尾位置, 多个范围, 签名
+type StreamingToken = (String, Option<u64>, u64, u64, Option<ByteRanges>, Option<UrlSignature>);
 #[inline]
 fn from_streaming_token(
-    StreamingCallbackToken { path, token }: StreamingCallbackToken,
-) -> Result<(String, u64, u64), ()> {
+    StreamingCallbackToken { path, mut token }: StreamingCallbackToken,
+) -> Result<StreamingToken, ()> {
+    let version = match token.remove("version") {
+        Some(version) => Some(version.parse::<u64>().map_err(|_| ())?),
+        None => None,
+    };
+    let ranges = match token.remove("ranges") {
+        Some(ranges) => Some(
+            ranges
+                .split(',')
+                .map(|range| match range.split_once('-') {
+                    Some((start, end)) => match (start.parse::<usize>(), end.parse::<usize>()) {
+                        (Ok(start), Ok(end)) if start <= end => Ok((start, end + 1)),
+                        _ => Err(()),
+                    },
+                    None => Err(()),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        None => None,
+    };
+    let signature = match (token.remove("sig"), token.remove("exp")) {
+        (Some(sig), Some(exp)) => Some((sig, exp.parse::<u64>().map_err(|_| ())?)),
+        _ => None,
+    };
     match (
         token.get("start").map(|s| s.parse()),
         token.get("end").map(|e| e.parse()),
     ) {
-        (Some(Ok(start)), Some(Ok(end))) => Ok((path, start, end)),
+        (Some(Ok(start)), Some(Ok(end))) => Ok((path, version, start, end, ranges, signature)),
         _ => Err(()),
     }
 }
@@ -282,8 +899,8 @@ fn http_streaming(token: StreamingCallbackToken) -> StreamingCallbackHttpRespons
     //     start,
     //     end,
     // );
-    let (path, start, end) = match from_streaming_token(token) {
-        Ok((path, start, end)) => (path, start, end),
+    let (path, version, start, end, ranges, signature) = match from_streaming_token(token) {
+        Ok(token) => token,
         _ => return StreamingCallbackHttpResponse::empty(),
     };
     if start == end {
@@ -293,8 +910,21 @@ fn http_streaming(token: StreamingCallbackToken) -> StreamingCallbackHttpRespons
             token: None,
         };
     }
+    let caller = ic_canister_kit::identity::caller();
+    let now = ic_canister_kit::times::now();
     crate::stable::with_state(|state| {
-        let file = state.business_assets_get_file(&path);
+        let file = match version {
+            Some(version) => state.business_assets_get_version(&path, version),
+            None => state.business_assets_get_file(&path),
+        }
+        .filter(|file| file.status == AssetStatus::Committed)
+        .filter(|file| !file.expired(now))
+        .filter(|file| {
+            state.business_path_permitted(&file.path, &caller, ACTION_BUSINESS_QUERY)
+                || signature
+                    .as_ref()
+                    .is_some_and(|(sig, exp)| state.business_url_verified(&file.path, version, sig, *exp, now))
+        });
         if let Some(file) = file {
             let asset = state.business_assets_get(&file.hash);
             if let Some(asset) = asset {
@@ -306,11 +936,27 @@ fn http_streaming(token: StreamingCallbackToken) -> StreamingCallbackHttpRespons
                     // 响应的范围太大了, 缩短为最大长度, 此时应当继续流式响应
                     streaming_end = offset + MAX_RESPONSE_LENGTH; // ! 末尾位置 不包含
                 }
+                let (body, ranges) = match ranges {
+                    Some(ranges) => {
+                        let multipart = MultipartRanges::new(&file, ranges);
+                        (
+                            multipart.read(offset, streaming_end, |offset, size| {
+                                asset.slice(&file.hash, file.size, offset, size)
+                            }),
+                            Some(multipart.ranges()),
+                        )
+                    }
+                    None => (
+                        asset
+                            .slice(&file.hash, file.size, offset, streaming_end - offset)
+                            .to_vec(),
+                        None,
+                    ),
+                };
                 return StreamingCallbackHttpResponse {
-                    body: asset
-                        .slice(&file.hash, file.size, offset, streaming_end - offset)
-                        .to_vec(),
-                    token: ((streaming_end as u64) < end).then(|| to_streaming_token(path, streaming_end as u64, end)),
+                    body,
+                    token: ((streaming_end as u64) < end)
+                        .then(|| to_streaming_token(path, version, streaming_end as u64, end, ranges, signature)),
                 };
             }
         }
@@ -320,3 +966,226 @@ fn http_streaming(token: StreamingCallbackToken) -> StreamingCallbackHttpRespons
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_version_param() {
+        assert_eq!(request_version("version=3"), Some(Some(3)));
+        assert_eq!(request_version("attachment=a.txt&version=3"), Some(Some(3)));
+        assert_eq!(request_version("version=latest"), Some(None));
+        assert_eq!(request_version("attachment=a.txt"), None);
+    }
+
+    #[test]
+    fn should_parse_signature_param() {
+        assert_eq!(request_signature("sig=abcd&exp=100"), Some(("abcd".to_string(), 100)));
+        assert_eq!(
+            request_signature("version=3&exp=100&sig=abcd"),
+            Some(("abcd".to_string(), 100))
+        );
+        assert_eq!(request_signature("sig=abcd"), None);
+        assert_eq!(request_signature("sig=abcd&exp=soon"), None);
+    }
+
+    #[test]
+    fn should_parse_multipart_file() {
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhello\r\n--xyz\r\nContent-Disposition: form-data; name=\"file\"; filename=\"C:\\docs\\a.txt\"\r\nContent-Type: text/plain\r\n\r\n1\r\n23\r\n--xyz--\r\n";
+        let file = multipart_file("multipart/form-data; boundary=xyz", body).unwrap();
+        assert_eq!(file.filename, "a.txt");
+        assert_eq!(file.data, b"1\r\n23");
+        assert_eq!(file.headers[1], ("Content-Type".to_string(), "text/plain".to_string()));
+
+        assert!(multipart_file("multipart/form-data; boundary=\"xyz\"", body).is_some());
+        assert!(multipart_file("multipart/form-data; boundary=abc", body).is_none());
+        assert!(multipart_file("text/plain", body).is_none());
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhello\r\n--xyz--\r\n";
+        assert!(multipart_file("multipart/form-data; boundary=xyz", body).is_none());
+    }
+
+    #[test]
+    fn should_map_upload_error_code() {
+        assert!(is_upload_method("put") && is_upload_method("POST") && !is_upload_method("GET"));
+        assert_eq!(request_token("a=1&token=abc"), Some("abc".to_string()));
+        assert_eq!(upload_error_code(&StorageError::InvalidToken("".into())), 401);
+        assert_eq!(upload_error_code(&StorageError::TooLarge { size: 2, max: 1 }), 413);
+        assert_eq!(upload_error_code(&StorageError::InvalidPath("".into())), 400);
+    }
+
+    #[test]
+    fn should_answer_preflight() {
+        let (mut code, mut headers) = (200, HashMap::new());
+        let request_headers = HashMap::from([("Access-Control-Request-Headers".to_string(), "x-token".to_string())]);
+        assert!(preflight(&request_headers, &mut code, &mut headers).is_empty());
+        assert_eq!(code, 204);
+        assert_eq!(headers["Access-Control-Allow-Headers"], "x-token");
+        assert_eq!(headers["Access-Control-Allow-Methods"], ALLOWED_METHODS);
+
+        let (mut code, mut headers) = (200, HashMap::new());
+        method_not_allowed(&mut code, &mut headers);
+        assert_eq!(code, 405);
+        assert_eq!(headers["Allow"], ALLOWED_METHODS);
+    }
+
+    #[test]
+    fn should_parse_single_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), RequestRange::Partial(0, 100));
+        assert_eq!(parse_range("bytes=500-", 1000), RequestRange::Partial(500, 1000));
+        assert_eq!(parse_range("bytes=-100", 1000), RequestRange::Partial(900, 1000));
+        assert_eq!(parse_range("bytes=900-2000", 1000), RequestRange::Partial(900, 1000));
+        assert_eq!(parse_range("bytes=-2000", 1000), RequestRange::Partial(0, 1000));
+    }
+
+    #[test]
+    fn should_reject_unsatisfiable_range() {
+        assert_eq!(parse_range("bytes=1000-", 1000), RequestRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=1000-1100", 1000), RequestRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), RequestRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn should_ignore_invalid_range() {
+        assert_eq!(parse_range("items=0-99", 1000), RequestRange::Full);
+        assert_eq!(parse_range("bytes=99-0", 1000), RequestRange::Full);
+        assert_eq!(parse_range("bytes=-", 1000), RequestRange::Full);
+        assert_eq!(parse_range("bytes=a-b", 1000), RequestRange::Full);
+        assert_eq!(parse_range("bytes=0-99,a-b", 1000), RequestRange::Full);
+    }
+
+    #[test]
+    fn should_reject_empty_range() {
+        let file = AssetFile {
+            path: "/empty.txt".into(),
+            created: 0.into(),
+            modified: 0.into(),
+            headers: vec![],
+            hash: HashDigest::default(),
+            size: 0,
+            status: AssetStatus::Committed,
+            uploader: None,
+            expires_at: None,
+        };
+        let request_headers = HashMap::from([("range".to_string(), "bytes=-100".to_string())]);
+        let (mut code, mut headers) = (200, HashMap::new());
+        let range = set_headers(
+            "/empty.txt",
+            "",
+            &request_headers,
+            &file,
+            MAX_RESPONSE_LENGTH,
+            &mut code,
+            &mut headers,
+        );
+        assert!(range.is_none());
+        assert_eq!(code, 416);
+        assert_eq!(headers["Content-Range"], "bytes */0");
+
+        let request_headers = HashMap::from([("range".to_string(), "bytes=-1,-2".to_string())]);
+        let (mut code, mut headers) = (200, HashMap::new());
+        let range = set_headers(
+            "/empty.txt",
+            "",
+            &request_headers,
+            &file,
+            MAX_RESPONSE_LENGTH,
+            &mut code,
+            &mut headers,
+        );
+        assert!(range.is_none());
+        assert_eq!(code, 416);
+    }
+
+    #[test]
+    fn should_parse_multiple_ranges() {
+        assert_eq!(
+            parse_range("bytes=0-99, 500-599", 1000),
+            RequestRange::Multiple(vec![(0, 100), (500, 600)])
+        );
+        assert_eq!(parse_range("bytes=0-99,2000-2099", 1000), RequestRange::Partial(0, 100));
+        assert_eq!(parse_range("bytes=1000-,2000-2099", 1000), RequestRange::Unsatisfiable);
+        let too_many = (0..=MAX_RANGES)
+            .map(|i| format!("{i}-{i}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(parse_range(&format!("bytes={too_many}"), 1000), RequestRange::Full);
+    }
+
+    #[test]
+    fn should_read_multipart_ranges() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let file = AssetFile {
+            path: "/test.bin".into(),
+            created: 0.into(),
+            modified: 0.into(),
+            headers: vec![("Content-Type".into(), "application/octet-stream".into())],
+            hash: HashDigest::default(),
+            size: data.len() as u64,
+            status: AssetStatus::Committed,
+            uploader: None,
+            expires_at: None,
+        };
+        let multipart = MultipartRanges::new(&file, vec![(0, 2), (98, 100)]);
+        let slice = |offset: usize, size: usize| Cow::Borrowed(&data[offset..offset + size]);
+
+        let boundary = &multipart.boundary;
+        let expected = format!(
+            "--{boundary}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 0-1/100\r\n\r\n\u{0}\u{1}\r\n--{boundary}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 98-99/100\r\n\r\nbc\r\n--{boundary}--\r\n"
+        );
+        assert_eq!(multipart.length, expected.len());
+        assert_eq!(multipart.ranges(), "0-1,98-99");
+        assert_eq!(multipart.read(0, multipart.length, slice), expected.as_bytes());
+        assert_eq!(multipart.read(10, 150, slice), &expected.as_bytes()[10..150]);
+
+        let multipart = MultipartRanges::new(&file, vec![(0, 0), (98, 100)]);
+        assert_eq!(multipart.ranges(), "98-99");
+    }
+
+    #[test]
+    fn should_check_conditional_request() {
+        let file = AssetFile {
+            path: "/test.txt".into(),
+            created: 0.into(),
+            modified: 1_700_000_000_123_456_789.into(),
+            headers: vec![],
+            hash: HashDigest::default(),
+            size: 1,
+            status: AssetStatus::Committed,
+            uploader: None,
+            expires_at: None,
+        };
+        let last_modified = http_date(file.modified);
+        assert_eq!(last_modified, "Tue, 14 Nov 2023 22:13:20 GMT");
+
+        let headers = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        let etag = format!("\"{}\"", file.hash.hex());
+        assert!(!not_modified(&headers(&[]), &file));
+        assert!(not_modified(&headers(&[("If-None-Match", &etag)]), &file));
+        assert!(not_modified(
+            &headers(&[("if-none-match", &format!("\"other\", W/{etag}"))]),
+            &file
+        ));
+        assert!(not_modified(&headers(&[("If-None-Match", "*")]), &file));
+        assert!(!not_modified(&headers(&[("If-None-Match", "\"other\"")]), &file));
+        let updated = AssetFile {
+            headers: vec![("Content-Type".into(), "text/plain".into())],
+            ..file.clone()
+        };
+        assert!(!not_modified(&headers(&[("If-None-Match", &etag)]), &updated)); // 只修改 headers 也不能使用旧的缓存
+        assert!(not_modified(&headers(&[("If-Modified-Since", &last_modified)]), &file));
+        assert!(!not_modified(
+            &headers(&[("If-Modified-Since", "Tue, 14 Nov 2023 22:13:19 GMT")]),
+            &file
+        ));
+        assert!(!not_modified(
+            &headers(&[("If-None-Match", "\"other\""), ("If-Modified-Since", &last_modified)]),
+            &file
+        ));
+    }
+}
@@ -18,4 +18,6 @@ mod explore; // 核心模块
 
 mod http; // 核心模块
 
+mod certification; // 可信验证
+
 mod common; // 由于有 candid 方法，必须放最后
@@ -30,21 +30,117 @@ pub(super) mod immutable {
         fn business_hashed_find(&self) -> bool {
             ic_cdk::trap("Not supported operation by this version.")
         }
-        fn business_files(&self) -> Vec<crate::stable::QueryFile> {
+        fn business_files(&self, caller: &UserId) -> Vec<crate::stable::QueryFile> {
             ic_cdk::trap("Not supported operation by this version.")
         }
-        fn business_download(&self, path: String) -> Vec<u8> {
+        fn business_files_by_page(
+            &self,
+            page: &QueryPage,
+            prefix: String,
+            cursor: Option<String>,
+            sort: crate::stable::FileSort,
+            caller: &UserId,
+        ) -> Result<crate::stable::QueryFilePage, crate::stable::StorageError> {
             ic_cdk::trap("Not supported operation by this version.")
         }
-        fn business_download_by(&self, path: String, offset: u64, size: u64) -> Vec<u8> {
+        fn business_download(&self, path: String) -> Result<Vec<u8>, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_download_by(
+            &self,
+            path: String,
+            offset: u64,
+            size: u64,
+            version: Option<u64>,
+        ) -> Result<Vec<u8>, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_status(&self, session: u64) -> Option<crate::stable::UploadingStatus> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_file_versions(
+            &self,
+            path: String,
+        ) -> Result<Vec<crate::stable::QueryFileVersion>, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_version_retention(&self) -> crate::stable::VersionRetention {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_trash_list(
+            &self,
+            page: &QueryPage,
+            caller: &UserId,
+        ) -> Result<PageData<crate::stable::QueryTrashedFile>, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_trash_path(&self, id: u64) -> Option<String> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_list_dir(
+            &self,
+            path: String,
+            cursor: Option<String>,
+            limit: u32,
+            caller: &UserId,
+        ) -> Result<crate::stable::QueryDir, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_acl_list(&self) -> Vec<(String, crate::stable::PathAcl)> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_path_permitted(&self, path: &str, caller: &UserId, permission: &str) -> bool {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_usage_query(&self, user: UserId) -> crate::stable::QueryUsage {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_tokens(&self) -> Vec<crate::stable::QueryUploadToken> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_url_secret_ready(&self) -> bool {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_url_verified(
+            &self,
+            path: &str,
+            version: Option<u64>,
+            sig: &str,
+            exp: u64,
+            now: TimestampNanos,
+        ) -> bool {
             ic_cdk::trap("Not supported operation by this version.")
         }
 
         // 内部使用的接口
-        fn business_assets_get_file(&self, path: &str) -> Option<&crate::stable::AssetFile> {
+        fn business_assets_get_file(&self, path: &str) -> Option<crate::stable::AssetFile> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_assets_get(&self, hash: &crate::stable::HashDigest) -> Option<crate::stable::AssetData> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_assets_get_version(&self, path: &str, version: u64) -> Option<crate::stable::AssetFile> {
             ic_cdk::trap("Not supported operation by this version.")
         }
-        fn business_assets_get(&self, hash: &crate::stable::HashDigest) -> Option<&crate::stable::AssetData> {
+        fn business_assets_files_after(&self, path: Option<String>, limit: usize) -> Vec<crate::stable::AssetFile> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_committing(&self) -> bool {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_digesting(&self) -> bool {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_verifying(&self) -> bool {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_deleting(&self) -> bool {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_purging(&self) -> bool {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_trash_emptying(&self) -> bool {
             ic_cdk::trap("Not supported operation by this version.")
         }
     }
@@ -54,22 +150,115 @@ pub(super) mod immutable {
         fn business_hashed_find(&self) -> bool {
             self.get().business_hashed_find()
         }
-        fn business_files(&self) -> Vec<QueryFile> {
-            self.get().business_files()
+        fn business_files(&self, caller: &UserId) -> Vec<QueryFile> {
+            self.get().business_files(caller)
         }
-        fn business_download(&self, path: String) -> Vec<u8> {
+        fn business_files_by_page(
+            &self,
+            page: &QueryPage,
+            prefix: String,
+            cursor: Option<String>,
+            sort: FileSort,
+            caller: &UserId,
+        ) -> Result<QueryFilePage, StorageError> {
+            self.get().business_files_by_page(page, prefix, cursor, sort, caller)
+        }
+        fn business_download(&self, path: String) -> Result<Vec<u8>, StorageError> {
             self.get().business_download(path)
         }
-        fn business_download_by(&self, path: String, offset: u64, size: u64) -> Vec<u8> {
-            self.get().business_download_by(path, offset, size)
+        fn business_download_by(
+            &self,
+            path: String,
+            offset: u64,
+            size: u64,
+            version: Option<u64>,
+        ) -> Result<Vec<u8>, StorageError> {
+            self.get().business_download_by(path, offset, size, version)
+        }
+        fn business_upload_status(&self, session: u64) -> Option<UploadingStatus> {
+            self.get().business_upload_status(session)
+        }
+        fn business_file_versions(&self, path: String) -> Result<Vec<QueryFileVersion>, StorageError> {
+            self.get().business_file_versions(path)
+        }
+        fn business_version_retention(&self) -> VersionRetention {
+            self.get().business_version_retention()
+        }
+        fn business_trash_list(
+            &self,
+            page: &QueryPage,
+            caller: &UserId,
+        ) -> Result<PageData<QueryTrashedFile>, StorageError> {
+            self.get().business_trash_list(page, caller)
+        }
+        fn business_trash_path(&self, id: u64) -> Option<String> {
+            self.get().business_trash_path(id)
+        }
+        fn business_list_dir(
+            &self,
+            path: String,
+            cursor: Option<String>,
+            limit: u32,
+            caller: &UserId,
+        ) -> Result<QueryDir, StorageError> {
+            self.get().business_list_dir(path, cursor, limit, caller)
+        }
+        fn business_acl_list(&self) -> Vec<(String, PathAcl)> {
+            self.get().business_acl_list()
+        }
+        fn business_path_permitted(&self, path: &str, caller: &UserId, permission: &str) -> bool {
+            self.get().business_path_permitted(path, caller, permission)
+        }
+        fn business_usage_query(&self, user: UserId) -> QueryUsage {
+            self.get().business_usage_query(user)
+        }
+        fn business_upload_tokens(&self) -> Vec<QueryUploadToken> {
+            self.get().business_upload_tokens()
+        }
+        fn business_url_secret_ready(&self) -> bool {
+            self.get().business_url_secret_ready()
+        }
+        fn business_url_verified(
+            &self,
+            path: &str,
+            version: Option<u64>,
+            sig: &str,
+            exp: u64,
+            now: TimestampNanos,
+        ) -> bool {
+            self.get().business_url_verified(path, version, sig, exp, now)
         }
 
-        fn business_assets_get_file(&self, path: &str) -> Option<&AssetFile> {
+        fn business_assets_get_file(&self, path: &str) -> Option<AssetFile> {
             self.get().business_assets_get_file(path)
         }
-        fn business_assets_get(&self, hash: &HashDigest) -> Option<&AssetData> {
+        fn business_assets_get(&self, hash: &HashDigest) -> Option<AssetData> {
             self.get().business_assets_get(hash)
         }
+        fn business_assets_get_version(&self, path: &str, version: u64) -> Option<AssetFile> {
+            self.get().business_assets_get_version(path, version)
+        }
+        fn business_assets_files_after(&self, path: Option<String>, limit: usize) -> Vec<AssetFile> {
+            self.get().business_assets_files_after(path, limit)
+        }
+        fn business_committing(&self) -> bool {
+            self.get().business_committing()
+        }
+        fn business_digesting(&self) -> bool {
+            self.get().business_digesting()
+        }
+        fn business_verifying(&self) -> bool {
+            self.get().business_verifying()
+        }
+        fn business_deleting(&self) -> bool {
+            self.get().business_deleting()
+        }
+        fn business_purging(&self) -> bool {
+            self.get().business_purging()
+        }
+        fn business_trash_emptying(&self) -> bool {
+            self.get().business_trash_emptying()
+        }
     }
 }
 pub use immutable::Business;
@@ -90,10 +279,184 @@ pub mod mutable {
         fn business_hashed_update(&mut self, hashed: bool) {
             ic_cdk::trap("Not supported operation by this version.")
         }
-        fn business_upload(&mut self, args: Vec<crate::stable::UploadingArg>) {
+        fn business_upload(
+            &mut self,
+            args: Vec<crate::stable::UploadingArg>,
+        ) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_begin(
+            &mut self,
+            arg: crate::stable::UploadBeginArg,
+        ) -> Result<u64, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_put(
+            &mut self,
+            args: Vec<crate::stable::UploadChunkArg>,
+            caller: UserId,
+        ) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_commit(
+            &mut self,
+            session: u64,
+            caller: UserId,
+        ) -> Result<crate::stable::UploadCommitted, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_abort(&mut self, session: u64, caller: UserId) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_expire(&mut self, now: TimestampNanos) -> Vec<(u64, String)> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_challenge(
+            &mut self,
+            hash: crate::stable::HashDigest,
+            random: [u8; 32],
+        ) -> Option<crate::stable::UploadChallenge> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_prove(
+            &mut self,
+            arg: crate::stable::UploadProofArg,
+            now: TimestampNanos,
+            caller: UserId,
+        ) -> Result<(), crate::stable::StorageError> {
             ic_cdk::trap("Not supported operation by this version.")
         }
-        fn business_delete(&mut self, names: Vec<String>) {
+        fn business_restore_version(
+            &mut self,
+            path: String,
+            version: u64,
+            now: TimestampNanos,
+            caller: UserId,
+        ) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_version_retention_update(
+            &mut self,
+            retention: crate::stable::VersionRetention,
+        ) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_versions_expire(&mut self, now: TimestampNanos) -> u64 {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_acl_update(
+            &mut self,
+            prefix: String,
+            acl: Option<crate::stable::PathAcl>,
+        ) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_delete(&mut self, names: Vec<String>, now: TimestampNanos, caller: UserId) {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_move(
+            &mut self,
+            from: String,
+            to: String,
+            overwrite: bool,
+            now: TimestampNanos,
+        ) -> Result<Vec<String>, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_copy(
+            &mut self,
+            from: String,
+            to: String,
+            caller: UserId,
+        ) -> Result<Vec<String>, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_quota_update(
+            &mut self,
+            target: crate::stable::QuotaTarget,
+            quota: Option<crate::stable::StorageQuota>,
+        ) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_token_mint(
+            &mut self,
+            arg: crate::stable::UploadTokenArg,
+            random: [u8; 32],
+            now: TimestampNanos,
+            issuer: UserId,
+        ) -> Result<crate::stable::UploadToken, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_token_revoke(&mut self, id: u64) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_upload_by_token(
+            &mut self,
+            token: &str,
+            args: Vec<crate::stable::UploadingArg>,
+            now: TimestampNanos,
+        ) -> Result<u64, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_sign_url(
+            &mut self,
+            path: String,
+            version: Option<u64>,
+            ttl: DurationNanos,
+            now: TimestampNanos,
+            random: Option<[u8; 32]>,
+        ) -> Result<crate::stable::SignedUrl, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_update_headers(
+            &mut self,
+            path: String,
+            headers: Vec<(String, String)>,
+            now: TimestampNanos,
+        ) -> Result<Vec<String>, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_delete_dir(
+            &mut self,
+            path: String,
+            recursive: bool,
+            caller: UserId,
+        ) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_trash_restore(
+            &mut self,
+            id: u64,
+            to: Option<String>,
+            caller: UserId,
+        ) -> Result<String, crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_trash_empty(&mut self, ids: Option<Vec<u64>>) -> Result<(), crate::stable::StorageError> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_trash_purge(&mut self, now: TimestampNanos) -> u64 {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_files_expire(&mut self, now: TimestampNanos) -> Vec<String> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_purge_orphans(&mut self) -> u64 {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_purge_step(&mut self) -> u64 {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_commit(&mut self) -> Vec<String> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_digest(&mut self) -> Vec<(String, Result<(), crate::stable::StorageError>)> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_verify(&mut self) -> Vec<(crate::stable::HashDigest, Vec<String>)> {
+            ic_cdk::trap("Not supported operation by this version.")
+        }
+        fn business_delete_dirs(&mut self, now: TimestampNanos) -> Vec<String> {
             ic_cdk::trap("Not supported operation by this version.")
         }
     }
@@ -106,11 +469,150 @@ pub mod mutable {
         fn business_hashed_update(&mut self, hashed: bool) {
             self.get_mut().business_hashed_update(hashed)
         }
-        fn business_upload(&mut self, args: Vec<UploadingArg>) {
+        fn business_upload(&mut self, args: Vec<UploadingArg>) -> Result<(), StorageError> {
             self.get_mut().business_upload(args)
         }
-        fn business_delete(&mut self, names: Vec<String>) {
-            self.get_mut().business_delete(names)
+        fn business_upload_begin(&mut self, arg: UploadBeginArg) -> Result<u64, StorageError> {
+            self.get_mut().business_upload_begin(arg)
+        }
+        fn business_upload_put(&mut self, args: Vec<UploadChunkArg>, caller: UserId) -> Result<(), StorageError> {
+            self.get_mut().business_upload_put(args, caller)
+        }
+        fn business_upload_commit(&mut self, session: u64, caller: UserId) -> Result<UploadCommitted, StorageError> {
+            self.get_mut().business_upload_commit(session, caller)
+        }
+        fn business_upload_abort(&mut self, session: u64, caller: UserId) -> Result<(), StorageError> {
+            self.get_mut().business_upload_abort(session, caller)
+        }
+        fn business_upload_expire(&mut self, now: TimestampNanos) -> Vec<(u64, String)> {
+            self.get_mut().business_upload_expire(now)
+        }
+        fn business_upload_challenge(&mut self, hash: HashDigest, random: [u8; 32]) -> Option<UploadChallenge> {
+            self.get_mut().business_upload_challenge(hash, random)
+        }
+        fn business_upload_prove(
+            &mut self,
+            arg: UploadProofArg,
+            now: TimestampNanos,
+            caller: UserId,
+        ) -> Result<(), StorageError> {
+            self.get_mut().business_upload_prove(arg, now, caller)
+        }
+        fn business_restore_version(
+            &mut self,
+            path: String,
+            version: u64,
+            now: TimestampNanos,
+            caller: UserId,
+        ) -> Result<(), StorageError> {
+            self.get_mut().business_restore_version(path, version, now, caller)
+        }
+        fn business_version_retention_update(&mut self, retention: VersionRetention) -> Result<(), StorageError> {
+            self.get_mut().business_version_retention_update(retention)
+        }
+        fn business_versions_expire(&mut self, now: TimestampNanos) -> u64 {
+            self.get_mut().business_versions_expire(now)
+        }
+        fn business_acl_update(&mut self, prefix: String, acl: Option<PathAcl>) -> Result<(), StorageError> {
+            self.get_mut().business_acl_update(prefix, acl)
+        }
+        fn business_delete(&mut self, names: Vec<String>, now: TimestampNanos, caller: UserId) {
+            self.get_mut().business_delete(names, now, caller)
+        }
+        fn business_move(
+            &mut self,
+            from: String,
+            to: String,
+            overwrite: bool,
+            now: TimestampNanos,
+        ) -> Result<Vec<String>, StorageError> {
+            self.get_mut().business_move(from, to, overwrite, now)
+        }
+        fn business_copy(&mut self, from: String, to: String, caller: UserId) -> Result<Vec<String>, StorageError> {
+            self.get_mut().business_copy(from, to, caller)
+        }
+        fn business_quota_update(
+            &mut self,
+            target: QuotaTarget,
+            quota: Option<StorageQuota>,
+        ) -> Result<(), StorageError> {
+            self.get_mut().business_quota_update(target, quota)
+        }
+        fn business_upload_token_mint(
+            &mut self,
+            arg: UploadTokenArg,
+            random: [u8; 32],
+            now: TimestampNanos,
+            issuer: UserId,
+        ) -> Result<UploadToken, StorageError> {
+            self.get_mut().business_upload_token_mint(arg, random, now, issuer)
+        }
+        fn business_upload_token_revoke(&mut self, id: u64) -> Result<(), StorageError> {
+            self.get_mut().business_upload_token_revoke(id)
+        }
+        fn business_upload_by_token(
+            &mut self,
+            token: &str,
+            args: Vec<UploadingArg>,
+            now: TimestampNanos,
+        ) -> Result<u64, StorageError> {
+            self.get_mut().business_upload_by_token(token, args, now)
+        }
+        fn business_sign_url(
+            &mut self,
+            path: String,
+            version: Option<u64>,
+            ttl: DurationNanos,
+            now: TimestampNanos,
+            random: Option<[u8; 32]>,
+        ) -> Result<SignedUrl, StorageError> {
+            self.get_mut().business_sign_url(path, version, ttl, now, random)
+        }
+        fn business_update_headers(
+            &mut self,
+            path: String,
+            headers: Vec<(String, String)>,
+            now: TimestampNanos,
+        ) -> Result<Vec<String>, StorageError> {
+            self.get_mut().business_update_headers(path, headers, now)
+        }
+        fn business_delete_dir(&mut self, path: String, recursive: bool, caller: UserId) -> Result<(), StorageError> {
+            self.get_mut().business_delete_dir(path, recursive, caller)
+        }
+        fn business_trash_restore(
+            &mut self,
+            id: u64,
+            to: Option<String>,
+            caller: UserId,
+        ) -> Result<String, StorageError> {
+            self.get_mut().business_trash_restore(id, to, caller)
+        }
+        fn business_trash_empty(&mut self, ids: Option<Vec<u64>>) -> Result<(), StorageError> {
+            self.get_mut().business_trash_empty(ids)
+        }
+        fn business_trash_purge(&mut self, now: TimestampNanos) -> u64 {
+            self.get_mut().business_trash_purge(now)
+        }
+        fn business_files_expire(&mut self, now: TimestampNanos) -> Vec<String> {
+            self.get_mut().business_files_expire(now)
+        }
+        fn business_purge_orphans(&mut self) -> u64 {
+            self.get_mut().business_purge_orphans()
+        }
+        fn business_purge_step(&mut self) -> u64 {
+            self.get_mut().business_purge_step()
+        }
+        fn business_commit(&mut self) -> Vec<String> {
+            self.get_mut().business_commit()
+        }
+        fn business_digest(&mut self) -> Vec<(String, Result<(), StorageError>)> {
+            self.get_mut().business_digest()
+        }
+        fn business_verify(&mut self) -> Vec<(HashDigest, Vec<String>)> {
+            self.get_mut().business_verify()
+        }
+        fn business_delete_dirs(&mut self, now: TimestampNanos) -> Vec<String> {
+            self.get_mut().business_delete_dirs(now)
         }
     }
 }
@@ -2,7 +2,10 @@ use std::collections::{HashMap, HashSet};
 
 use ic_canister_kit::types::*;
 
-use super::{ParsePermission, ParsePermissionError, business::immutable::GetImmutable, business::mutable::GetMutable};
+use super::{
+    Business, MutableBusiness, ParsePermission, ParsePermissionError, business::immutable::GetImmutable,
+    business::mutable::GetMutable,
+};
 use super::{State, schedule_task, with_state};
 
 /// 检查是否拥有某权限
@@ -23,6 +26,60 @@ pub fn check_permission(
     })
 }
 
+/// 检查是否拥有某权限, 返回业务错误
+pub fn check_business_permission(
+    permission: &str,
+    running: bool, // 是否要求必须处于正常运行状态
+) -> Result<(), super::StorageError> {
+    use super::StorageError;
+    let caller = ic_canister_kit::identity::caller();
+    with_state(|s| {
+        let _permission = s
+            .parse_permission(permission)
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        if !s.permission_has(&caller, &_permission) {
+            return Err(StorageError::PermissionDenied(permission.to_string()));
+        }
+        if running {
+            s.pause_must_be_running().map_err(StorageError::Paused)?;
+        }
+        Ok(())
+    })
+}
+
+/// 检查对路径是否拥有某权限, 返回业务错误
+/// 没有全局权限时, 需要路径的访问控制授予所有路径的权限
+pub fn check_business_path_permission(
+    permission: &str,
+    paths: &[String],
+    running: bool, // 是否要求必须处于正常运行状态
+) -> Result<(), super::StorageError> {
+    use super::StorageError;
+    let caller = ic_canister_kit::identity::caller();
+    with_state(|s| {
+        let _permission = s
+            .parse_permission(permission)
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        // 路径的规则对拥有全局权限的用户也生效
+        if (paths.is_empty() && !s.permission_has(&caller, &_permission))
+            || !paths
+                .iter()
+                .all(|path| s.business_path_permitted(path, &caller, permission))
+        {
+            return Err(StorageError::PermissionDenied(permission.to_string()));
+        }
+        if running {
+            s.pause_must_be_running().map_err(StorageError::Paused)?;
+        }
+        Ok(())
+    })
+}
+
+/// 不需要权限的接口, 只检查是否处于正常运行状态, 返回业务错误
+pub fn check_business_running() -> Result<(), super::StorageError> {
+    with_state(|s| s.pause_must_be_running()).map_err(super::StorageError::Paused)
+}
+
 impl Pausable<PauseReason> for State {
     // 查询
     fn pause_query(&self) -> &Option<PauseReason> {
@@ -116,6 +173,116 @@ async fn static_schedule_task() {
     ic_cdk::futures::spawn(async move { schedule_task(None).await });
 }
 
+/// 继续写入稳定内存, 每条消息写入有限的数据块, 剩余的数据在后续的消息中继续写入
+pub fn commit_assets() {
+    let committed = super::with_mut_state_without_record(|s| s.business_commit());
+    crate::certification::update_certified_assets(&committed); // * 写入完成的文件可以访问了
+
+    if with_state(|s| s.business_committing()) {
+        ic_canister_kit::functions::schedule::async_execute(async { commit_assets() });
+    } else {
+        verify_assets(); // * 写入完成后再校验
+    }
+}
+
+thread_local! {
+    static DIGESTING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) }; // 后台计算 hash 是否正在进行
+    static VERIFYING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) }; // 后台校验是否正在进行
+}
+
+/// 继续计算提交的上传数据的 hash, 每条消息计算有限的数据, 计算完成的数据再写入稳定内存
+pub fn digest_uploads() {
+    if DIGESTING.get() || !with_state(|s| s.business_digesting()) {
+        return;
+    }
+    DIGESTING.set(true);
+    ic_canister_kit::functions::schedule::async_execute(async { digest_uploads_step() });
+}
+
+fn digest_uploads_step() {
+    let digested = super::with_mut_state_without_record(|s| s.business_digest());
+    let mut paths = Vec::new();
+    for (path, result) in digested {
+        match result {
+            Ok(()) => paths.push(path),
+            Err(err) => {
+                let record_id = super::with_record_push(
+                    super::RecordTopics::HashMismatched.topic(),
+                    format!("upload digest failed: path: {path} error: {err}"),
+                );
+                super::with_record_update_done(record_id);
+            }
+        }
+    }
+    if !paths.is_empty() {
+        commit_assets(); // * 计算完成的数据写入稳定内存
+        crate::certification::update_certified_assets(&paths); // * 更新证书
+    }
+
+    if with_state(|s| s.business_digesting()) {
+        ic_canister_kit::functions::schedule::async_execute(async { digest_uploads_step() });
+    } else {
+        DIGESTING.set(false);
+    }
+}
+
+/// 后台校验直接采用上传 hash 保存的数据, 每条消息校验有限的数据块
+pub fn verify_assets() {
+    if VERIFYING.get() || !with_state(|s| s.business_verifying()) {
+        return;
+    }
+    VERIFYING.set(true);
+    ic_canister_kit::functions::schedule::async_execute(async { verify_assets_step() });
+}
+
+fn verify_assets_step() {
+    let mismatched = super::with_mut_state_without_record(|s| s.business_verify());
+    for (hash, paths) in mismatched {
+        let record_id = super::with_record_push(
+            super::RecordTopics::HashMismatched.topic(),
+            format!("hash mismatched: {} paths: [{}]", hash.hex(), paths.join(", ")),
+        );
+        super::with_record_update_done(record_id);
+        crate::certification::update_certified_assets(&paths); // * 校验失败的文件不再可信
+    }
+
+    if with_state(|s| s.business_verifying()) {
+        ic_canister_kit::functions::schedule::async_execute(async { verify_assets_step() });
+    } else {
+        VERIFYING.set(false);
+    }
+}
+
+/// 继续删除目录, 每条消息删除有限的文件, 剩余的文件在后续的消息中继续删除
+pub fn delete_dirs() {
+    let now = ic_canister_kit::times::now();
+    let deleted = super::with_mut_state_without_record(|s| s.business_delete_dirs(now));
+    crate::certification::update_certified_assets(&deleted); // * 删除的文件不能再访问
+
+    if with_state(|s| s.business_deleting()) {
+        ic_canister_kit::functions::schedule::async_execute(async { delete_dirs() });
+    }
+}
+
+/// 继续清除没有被引用的数据块, 每条消息扫描有限的数据块
+pub fn purge_orphans() {
+    super::with_mut_state_without_record(|s| s.business_purge_step());
+
+    if with_state(|s| s.business_purging()) {
+        ic_canister_kit::functions::schedule::async_execute(async { purge_orphans() });
+    }
+}
+
+/// 继续清空回收站, 每条消息清除有限的文件
+pub fn empty_trash() {
+    let now = ic_canister_kit::times::now();
+    super::with_mut_state_without_record(|s| s.business_trash_purge(now));
+
+    if with_state(|s| s.business_trash_emptying()) {
+        ic_canister_kit::functions::schedule::async_execute(async { empty_trash() });
+    }
+}
+
 pub trait ScheduleTask: Schedulable {
     fn schedule_stop(&self) {
         ic_canister_kit::functions::schedule::stop_schedule();
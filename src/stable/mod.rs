@@ -15,14 +15,16 @@ pub use business::*;
 
 mod v000;
 mod v001;
+mod v002;
 
 // ! 此处应该是最新的版本
 // !     👇👇 UPGRADE WARNING: 必须是当前代码的版本
-pub use v001::types::*;
+pub use v002::types::*;
 
 pub enum State {
     V0(Box<v000::types::InnerState>),
     V1(Box<v001::types::InnerState>),
+    V2(Box<v002::types::InnerState>),
     // *    👆👆 UPGRADE WARNING: 引入新版本
 }
 use State::*;
@@ -31,6 +33,7 @@ use State::*;
 pub enum InitArgs {
     V0(Box<v000::types::InitArg>),
     V1(Box<v001::types::InitArg>),
+    V2(Box<v002::types::InitArg>),
     // *    👆👆 UPGRADE WARNING: 引入新版本
 }
 
@@ -38,6 +41,7 @@ pub enum InitArgs {
 pub enum UpgradeArgs {
     V0(Box<v000::types::UpgradeArg>),
     V1(Box<v001::types::UpgradeArg>),
+    V2(Box<v002::types::UpgradeArg>),
     // *    👆👆 UPGRADE WARNING: 引入新版本
 }
 
@@ -48,12 +52,14 @@ impl Initial<Option<InitArgs>> for State {
             Some(args) => match (self, args) {
                 (V0(s), InitArgs::V0(arg)) => s.init(Some(*arg)),
                 (V1(s), InitArgs::V1(arg)) => s.init(Some(*arg)),
+                (V2(s), InitArgs::V2(arg)) => s.init(Some(*arg)),
                 // ! 👆👆 新增版本需要添加默认的数据
                 _ => ic_cdk::trap("version mismatched"),
             },
             None => match self {
                 V0(s) => s.init(None),
                 V1(s) => s.init(None),
+                V2(s) => s.init(None),
             },
         }
     }
@@ -66,7 +72,8 @@ impl Upgrade<Option<UpgradeArgs>> for State {
             // 进行升级操作, 不断地升到下一版本
             match self {
                 V0(s) => *self = V1(std::mem::take(&mut *s).into()), // -> V1
-                V1(_) => break 'outer,                               // same version do nothing
+                V1(s) => *self = V2(std::mem::take(&mut *s).into()), // -> V2
+                V2(_) => break 'outer,                               // same version do nothing
             }
         }
 
@@ -76,6 +83,7 @@ impl Upgrade<Option<UpgradeArgs>> for State {
                 match (self, args) {
                     (V0(s), UpgradeArgs::V0(arg)) => s.upgrade(Some(*arg)),
                     (V1(s), UpgradeArgs::V1(arg)) => s.upgrade(Some(*arg)),
+                    (V2(s), UpgradeArgs::V2(arg)) => s.upgrade(Some(*arg)),
                     // ! 👆👆 新增版本需要添加默认的数据
                     _ => ic_cdk::trap("version mismatched"),
                 }
@@ -83,6 +91,7 @@ impl Upgrade<Option<UpgradeArgs>> for State {
             None => match self {
                 V0(s) => s.upgrade(None),
                 V1(s) => s.upgrade(None),
+                V2(s) => s.upgrade(None),
             },
         }
     }
@@ -94,6 +103,7 @@ impl StateUpgrade<Option<UpgradeArgs>> for State {
         match self {
             V0(_) => 0,
             V1(_) => 1,
+            V2(_) => 2,
             // *   👆👆! 升级需要在此添加版本号
         }
     }
@@ -102,6 +112,7 @@ impl StateUpgrade<Option<UpgradeArgs>> for State {
         match version {
             0 => V0(Box::default()), // * 初始化
             1 => V1(Box::default()), // * 初始化
+            2 => V2(Box::default()), // * 初始化
             // ! 👆👆 新增版本需要添加默认的数据
             _ => ic_cdk::trap("unsupported version"),
         }
@@ -115,6 +126,7 @@ impl business::immutable::GetImmutable for State {
         match self {
             V0(s) => s.as_ref(), // * 获取不可变对象
             V1(s) => s.as_ref(), // * 获取不可变对象
+            V2(s) => s.as_ref(), // * 获取不可变对象
         }
     }
 }
@@ -124,6 +136,7 @@ impl business::mutable::GetMutable for State {
         match self {
             V0(s) => s.as_mut(), // * 获取可变对象
             V1(s) => s.as_mut(), // * 获取可变对象
+            V2(s) => s.as_mut(), // * 获取可变对象
         }
     }
 }
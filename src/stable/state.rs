@@ -34,7 +34,9 @@ fn initial(args: Option<InitArgs>) {
         s.init(args); // ! 初始化最新版本
         s.schedule_reload(); // * 重置定时任务
         s.record_update(record_id, format!("Version: {}", s.version()));
-    })
+    });
+
+    crate::certification::init_certified_assets(); // * 重置证书
 }
 
 // ==================== 升级时的恢复逻辑 ====================
@@ -63,6 +65,13 @@ fn post_upgrade(args: Option<UpgradeArgs>) {
             .borrow_mut()
             .record_update(record_id, format!("Next version: {version}"));
     });
+
+    crate::certification::init_certified_assets(); // * 重置证书
+    ic_canister_kit::functions::schedule::async_execute(async { super::digest_uploads() }); // * 继续计算升级前没有计算完的 hash
+    ic_canister_kit::functions::schedule::async_execute(async { super::commit_assets() }); // * 继续写入升级前没有完成的数据
+    ic_canister_kit::functions::schedule::async_execute(async { super::delete_dirs() }); // * 继续删除升级前没有删除完的目录
+    ic_canister_kit::functions::schedule::async_execute(async { super::empty_trash() }); // * 继续清空升级前没有清空的回收站
+    ic_canister_kit::functions::schedule::async_execute(async { super::purge_orphans() }); // * 继续清除升级前没有清除完的数据块
 }
 
 // ==================== 升级时的保存逻辑，下次升级执行 ====================
@@ -48,7 +48,7 @@ pub struct InnerState {
     pub files: HashMap<String, AssetFile>,      // key 是 path // ? 堆内存 序列化
     hashes: HashMap<HashDigest, HashedPath>, // key 是 hash, value 是 path, 没有 path 的数据是没有保存意义的 // ? 堆内存 序列化
 
-    uploading: HashMap<String, UploadingFile>, // key 是 path // ? 堆内存 序列化
+    pub(crate) uploading: HashMap<String, UploadingFile>, // key 是 path // ? 堆内存 序列化
 }
 
 impl Default for InnerState {
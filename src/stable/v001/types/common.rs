@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 pub type SliceOfHashDigest = [u8; 4 + 32];
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct HashDigest(pub(super) [u8; 32]);
+pub struct HashDigest(pub(crate) [u8; 32]);
 
 impl HashDigest {
     pub fn hex(&self) -> String {
@@ -0,0 +1,271 @@
+use super::super::business::*;
+use super::types::*;
+
+impl Business for InnerState {
+    fn business_hashed_find(&self) -> bool {
+        self.hashed
+    }
+    fn business_files(&self, caller: &UserId) -> Vec<QueryFile> {
+        self.files(caller)
+    }
+    fn business_files_by_page(
+        &self,
+        page: &QueryPage,
+        prefix: String,
+        cursor: Option<String>,
+        sort: FileSort,
+        caller: &UserId,
+    ) -> Result<QueryFilePage, StorageError> {
+        self.files_by_page(page, prefix, cursor, sort, caller)
+    }
+    fn business_download(&self, path: String) -> Result<Vec<u8>, StorageError> {
+        self.download(path, ic_canister_kit::times::now())
+    }
+    fn business_download_by(
+        &self,
+        path: String,
+        offset: u64,
+        size: u64,
+        version: Option<u64>,
+    ) -> Result<Vec<u8>, StorageError> {
+        self.download_by(path, offset, size, version, ic_canister_kit::times::now())
+    }
+    fn business_upload_status(&self, session: u64) -> Option<UploadingStatus> {
+        self.upload_status(session)
+    }
+    fn business_file_versions(&self, path: String) -> Result<Vec<QueryFileVersion>, StorageError> {
+        self.file_versions(path)
+    }
+    fn business_version_retention(&self) -> VersionRetention {
+        self.version_retention
+    }
+    fn business_trash_list(
+        &self,
+        page: &QueryPage,
+        caller: &UserId,
+    ) -> Result<PageData<QueryTrashedFile>, StorageError> {
+        self.trash_list(page, caller)
+    }
+    fn business_trash_path(&self, id: u64) -> Option<String> {
+        self.trash_path(id)
+    }
+    fn business_list_dir(
+        &self,
+        path: String,
+        cursor: Option<String>,
+        limit: u32,
+        caller: &UserId,
+    ) -> Result<QueryDir, StorageError> {
+        self.list_dir(path, cursor, limit, caller)
+    }
+    fn business_acl_list(&self) -> Vec<(String, PathAcl)> {
+        self.acl_list()
+    }
+    fn business_path_permitted(&self, path: &str, caller: &UserId, permission: &str) -> bool {
+        self.path_permitted(path, caller, permission)
+    }
+    fn business_usage_query(&self, user: UserId) -> QueryUsage {
+        self.usage_query(user)
+    }
+    fn business_upload_tokens(&self) -> Vec<QueryUploadToken> {
+        self.upload_tokens()
+    }
+    fn business_url_secret_ready(&self) -> bool {
+        self.url_secret_ready()
+    }
+    fn business_url_verified(
+        &self,
+        path: &str,
+        version: Option<u64>,
+        sig: &str,
+        exp: u64,
+        now: TimestampNanos,
+    ) -> bool {
+        self.url_verified(path, version, sig, exp, now)
+    }
+
+    fn business_assets_get_file(&self, path: &str) -> Option<AssetFile> {
+        self.files.get(&path.to_string())
+    }
+    fn business_assets_get(&self, hash: &HashDigest) -> Option<AssetData> {
+        self.assets.get(hash)
+    }
+    fn business_assets_get_version(&self, path: &str, version: u64) -> Option<AssetFile> {
+        self.version_file(path, version)
+    }
+    fn business_assets_files_after(&self, path: Option<String>, limit: usize) -> Vec<AssetFile> {
+        self.files_after(path, limit)
+    }
+    fn business_committing(&self) -> bool {
+        self.committing()
+    }
+    fn business_digesting(&self) -> bool {
+        self.digesting()
+    }
+    fn business_verifying(&self) -> bool {
+        self.verifying()
+    }
+    fn business_deleting(&self) -> bool {
+        self.deleting()
+    }
+    fn business_purging(&self) -> bool {
+        self.purging()
+    }
+    fn business_trash_emptying(&self) -> bool {
+        self.trash_emptying()
+    }
+}
+
+#[allow(clippy::panic)] // ? 允许回滚
+#[allow(clippy::unwrap_used)] // ? 允许回滚
+#[allow(clippy::expect_used)] // ? 允许回滚
+impl MutableBusiness for InnerState {
+    fn business_hashed_update(&mut self, hashed: bool) {
+        self.hashed = hashed;
+    }
+    fn business_upload(&mut self, args: Vec<UploadingArg>) -> Result<(), StorageError> {
+        for arg in args {
+            self.put_uploading(arg)?; // 出错时, 之前的数据块已经写入
+        }
+        Ok(())
+    }
+    fn business_upload_begin(&mut self, arg: UploadBeginArg) -> Result<u64, StorageError> {
+        self.upload_begin(arg)
+    }
+    fn business_upload_put(&mut self, args: Vec<UploadChunkArg>, caller: UserId) -> Result<(), StorageError> {
+        for arg in args {
+            self.upload_put(arg, &caller)?; // 出错时, 之前的数据块已经写入
+        }
+        Ok(())
+    }
+    fn business_upload_commit(&mut self, session: u64, caller: UserId) -> Result<UploadCommitted, StorageError> {
+        self.upload_commit(session, &caller)
+    }
+    fn business_upload_abort(&mut self, session: u64, caller: UserId) -> Result<(), StorageError> {
+        self.upload_abort(session, &caller)
+    }
+    fn business_upload_expire(&mut self, now: TimestampNanos) -> Vec<(u64, String)> {
+        self.upload_expire(now)
+    }
+    fn business_upload_challenge(&mut self, hash: HashDigest, random: [u8; 32]) -> Option<UploadChallenge> {
+        self.upload_challenge(hash, random)
+    }
+    fn business_upload_prove(
+        &mut self,
+        arg: UploadProofArg,
+        now: TimestampNanos,
+        caller: UserId,
+    ) -> Result<(), StorageError> {
+        self.upload_prove(arg, now, caller)
+    }
+    fn business_restore_version(
+        &mut self,
+        path: String,
+        version: u64,
+        now: TimestampNanos,
+        caller: UserId,
+    ) -> Result<(), StorageError> {
+        self.restore_version(path, version, now, Some(caller))
+    }
+    fn business_version_retention_update(&mut self, retention: VersionRetention) -> Result<(), StorageError> {
+        self.version_retention_update(retention)
+    }
+    fn business_acl_update(&mut self, prefix: String, acl: Option<PathAcl>) -> Result<(), StorageError> {
+        self.acl_update(prefix, acl)
+    }
+    fn business_versions_expire(&mut self, now: TimestampNanos) -> u64 {
+        self.versions_expire(now)
+    }
+    fn business_delete(&mut self, names: Vec<String>, now: TimestampNanos, caller: UserId) {
+        for name in names {
+            self.clean_uploading(&name);
+            self.trash_file(&name, now, Some(caller)); // 放入回收站
+        }
+    }
+    fn business_move(
+        &mut self,
+        from: String,
+        to: String,
+        overwrite: bool,
+        now: TimestampNanos,
+    ) -> Result<Vec<String>, StorageError> {
+        self.move_files(from, to, overwrite, now)
+    }
+    fn business_copy(&mut self, from: String, to: String, caller: UserId) -> Result<Vec<String>, StorageError> {
+        self.copy_files(from, to, Some(caller))
+    }
+    fn business_quota_update(&mut self, target: QuotaTarget, quota: Option<StorageQuota>) -> Result<(), StorageError> {
+        self.quota_update(target, quota)
+    }
+    fn business_upload_token_mint(
+        &mut self,
+        arg: UploadTokenArg,
+        random: [u8; 32],
+        now: TimestampNanos,
+        issuer: UserId,
+    ) -> Result<UploadToken, StorageError> {
+        self.upload_token_mint(arg, random, now, issuer)
+    }
+    fn business_upload_token_revoke(&mut self, id: u64) -> Result<(), StorageError> {
+        self.upload_token_revoke(id)
+    }
+    fn business_upload_by_token(
+        &mut self,
+        token: &str,
+        args: Vec<UploadingArg>,
+        now: TimestampNanos,
+    ) -> Result<u64, StorageError> {
+        self.upload_by_token(token, args, now)
+    }
+    fn business_sign_url(
+        &mut self,
+        path: String,
+        version: Option<u64>,
+        ttl: DurationNanos,
+        now: TimestampNanos,
+        random: Option<[u8; 32]>,
+    ) -> Result<SignedUrl, StorageError> {
+        self.sign_url(path, version, ttl, now, random)
+    }
+    fn business_update_headers(
+        &mut self,
+        path: String,
+        headers: Vec<(String, String)>,
+        now: TimestampNanos,
+    ) -> Result<Vec<String>, StorageError> {
+        self.update_headers(path, headers, now)
+    }
+    fn business_delete_dir(&mut self, path: String, recursive: bool, caller: UserId) -> Result<(), StorageError> {
+        self.delete_dir(path, recursive, Some(caller))
+    }
+    fn business_trash_restore(&mut self, id: u64, to: Option<String>, caller: UserId) -> Result<String, StorageError> {
+        self.trash_restore(id, to, caller)
+    }
+    fn business_trash_empty(&mut self, ids: Option<Vec<u64>>) -> Result<(), StorageError> {
+        self.trash_empty(ids)
+    }
+    fn business_trash_purge(&mut self, now: TimestampNanos) -> u64 {
+        self.trash_purge(now)
+    }
+    fn business_files_expire(&mut self, now: TimestampNanos) -> Vec<String> {
+        self.files_expire(now)
+    }
+    fn business_purge_orphans(&mut self) -> u64 {
+        self.purge_orphans()
+    }
+    fn business_purge_step(&mut self) -> u64 {
+        self.purge_step()
+    }
+    fn business_commit(&mut self) -> Vec<String> {
+        self.commit()
+    }
+    fn business_digest(&mut self) -> Vec<(String, Result<(), StorageError>)> {
+        self.digest()
+    }
+    fn business_verify(&mut self) -> Vec<(HashDigest, Vec<String>)> {
+        self.verify()
+    }
+    fn business_delete_dirs(&mut self, now: TimestampNanos) -> Vec<String> {
+        self.delete_dirs(now)
+    }
+}
@@ -0,0 +1,88 @@
+use ic_canister_kit::{common::option::display_option_by, times::now};
+
+use super::super::*;
+#[allow(unused)]
+use super::types::*;
+
+#[allow(unused)]
+#[allow(unused_variables)]
+pub async fn schedule_task(record_by: Option<CallerId>) {
+    // * 记录
+    let record_id = with_record_push(super::types::RecordTopics::Schedule.topic(), String::with_capacity(0));
+
+    // 如果有定时任务
+    ic_cdk::println!(
+        "{}: do schedule task... ({})",
+        display_option_by(&record_by, |p| p.to_text()),
+        now()
+    );
+
+    // ! 为了保证记录的完整性，不应当发生 panic
+    inner_task(record_by).await;
+
+    // * 记录
+    with_record_update_done(record_id);
+}
+
+async fn inner_task(caller: Option<CallerId>) {
+    ic_cdk::println!("do something: {:?}", caller.map(|c| c.to_text()));
+
+    // 还没有计算完 hash 的上传, 继续计算
+    digest_uploads();
+
+    // 还没有写完的数据, 继续写入
+    if with_state(|s| s.business_committing()) {
+        commit_assets();
+    }
+
+    // 没有删除完的目录, 继续删除
+    if with_state(|s| s.business_deleting()) {
+        delete_dirs();
+    }
+
+    // 没有清除完的数据块, 继续清除
+    if with_state(|s| s.business_purging()) {
+        purge_orphans();
+    }
+
+    // 没有校验完的数据, 继续校验
+    verify_assets();
+
+    // 清除过期的文件
+    let expired = with_mut_state_without_record(|s| s.business_files_expire(now()));
+    for path in expired.iter() {
+        let record_id = with_record_push(RecordTopics::ExpireFile.topic(), format!("expire file: {path}"));
+        with_record_update_done(record_id);
+    }
+    crate::certification::update_certified_assets(&expired); // * 过期的文件不能再访问
+
+    // 清除超过保留时间的回收站文件
+    let purged = with_mut_state_without_record(|s| s.business_trash_purge(now()));
+    if 0 < purged {
+        let record_id = with_record_push(RecordTopics::DeleteFile.topic(), format!("purge trash: {purged}"));
+        with_record_update_done(record_id);
+    }
+    if with_state(|s| s.business_trash_emptying()) {
+        empty_trash();
+    }
+
+    // 按保留策略清除历史版本
+    let expired = with_mut_state_without_record(|s| s.business_versions_expire(now()));
+    if 0 < expired {
+        let record_id = with_record_push(
+            RecordTopics::ExpireVersion.topic(),
+            format!("expire versions: {expired}"),
+        );
+        with_record_update_done(record_id);
+    }
+
+    // 清除长时间没有上传数据的会话
+    let expired = with_mut_state_without_record(|s| s.business_upload_expire(now()));
+    for (session, path) in expired {
+        let record_id = with_record_push(
+            RecordTopics::ExpireUploading.topic(),
+            format!("expire uploading: session: {session} path: {path}"),
+        );
+        with_record_update_done(record_id);
+    }
+}
@@ -0,0 +1,3044 @@
+pub use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+#[allow(unused)]
+pub use super::super::{Business, MutableBusiness, ParsePermission, ScheduleTask};
+
+#[allow(unused)]
+pub use super::super::business::*;
+#[allow(unused)]
+pub use super::business::*;
+#[allow(unused)]
+pub use super::permission::*;
+#[allow(unused)]
+pub use super::schedule::schedule_task;
+
+mod _init;
+pub use _init::*;
+mod _upgrade;
+pub use _upgrade::*;
+mod _topic;
+pub use _topic::*;
+mod _canister_kit;
+pub use _canister_kit::*;
+
+// 业务类型
+mod common;
+pub use common::*;
+mod assets;
+pub use assets::*;
+mod upload;
+pub use upload::*;
+mod error;
+pub use error::*;
+mod version;
+pub use version::*;
+mod trash;
+pub use trash::*;
+mod acl;
+pub use acl::*;
+mod quota;
+pub use quota::*;
+mod token;
+pub use token::*;
+mod signed;
+pub use signed::*;
+mod stable;
+use stable::*;
+
+// 能序列化的和不能序列化的放在一起
+// 其中不能序列化的采用如下注解
+// #[serde(skip)] 默认初始化方式
+// #[serde(skip, default="init_xxx_data")] 指定初始化方式
+// ! 如果使用 ic-stable-structures 提供的稳定内存，不能变更 memory_id 的使用类型，否则会出现各个版本不兼容，数据会被清空
+#[derive(Serialize, Deserialize)]
+pub struct InnerState {
+    pub canister_kit: CanisterKit, // 框架需要的数据 // ? 堆内存 序列化
+
+    // 业务数据
+    pub hashed: bool, // 是否相信上传的 hash 值，true -> 直接采用接口传递的 hash 值， false -> 数据上传完成后，需要罐子再 hash 一次 // ? 堆内存 序列化
+
+    #[serde(skip, default = "init_assets_index_data")]
+    pub assets: StableBTreeMap<HashDigest, AssetData>, // key 是 hash // ? 稳定内存
+    #[serde(skip, default = "init_files_data")]
+    pub files: StableBTreeMap<String, AssetFile>, // key 是 path // ? 稳定内存
+    #[serde(skip, default = "init_hashes_data")]
+    hashes: StableBTreeMap<HashDigest, HashedPath>, // key 是 hash, value 是 path, 没有 path 的数据是没有保存意义的 // ? 稳定内存
+    #[serde(skip, default = "init_versions_data")]
+    versions: StableBTreeMap<String, FileVersions>, // key 是 path, 被覆盖的内容, 在 hashes 中以 version_ref 引用 // ? 稳定内存
+    #[serde(skip, default = "init_trash_data")]
+    trash: StableBTreeMap<u64, TrashedFile>, // key 是删除序号, 被删除的文件, 在 hashes 中以 trash_ref 引用 // ? 稳定内存
+
+    pub(super) uploading: HashMap<String, UploadingFile>, // key 是 path // ? 堆内存 序列化
+    #[serde(default)]
+    pub(super) digesting: BTreeMap<String, bool>, // key 是 path, 已经提交等待计算 hash 的上传, value 是否需要与声明的 hash 一致 // ? 堆内存 序列化
+    #[serde(skip)]
+    digesters: HashMap<String, UploadDigester>, // 正在计算的 hash, 升级后重新开始 // ? 堆内存 不序列化
+
+    pub(super) committing: HashMap<HashDigest, AssetCommit>, // key 是 hash, 还没有完整写入稳定内存的数据 // ? 堆内存 序列化
+
+    pub(super) next_session: u64,         // 上传会话的序号 // ? 堆内存 序列化
+    pub uploading_timeout: DurationNanos, // 上传会话长时间没有上传数据就会被清除 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) challenges: HashMap<HashDigest, UploadChallengeData>, // key 是 nonce, 等待证明持有数据的挑战 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) verifying: BTreeSet<HashDigest>, // 直接采用上传 hash 保存的数据, 等待后台校验 // ? 堆内存 序列化
+    #[serde(skip)]
+    verifier: Option<AssetVerifier>, // 正在校验的数据, 升级后重新开始 // ? 堆内存 不序列化
+
+    #[serde(default)]
+    pub(super) deleting: VecDeque<DeletingDir>, // 等待分批删除的目录 // ? 堆内存 序列化
+    #[serde(default)]
+    pub(super) purging: Option<OrphanPurge>, // 正在分批清除没有被引用的数据块 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub version_retention: VersionRetention, // 历史版本的保留策略 // ? 堆内存 序列化
+    #[serde(default)]
+    pub(super) versions_cursor: Option<String>, // 定时任务清理历史版本的进度 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) next_trash: u64, // 回收站的删除序号 // ? 堆内存 序列化
+    #[serde(default = "default_trash_retention")]
+    pub trash_retention: DurationNanos, // 回收站的文件超过该时间会被彻底清除 // ? 堆内存 序列化
+    #[serde(default)]
+    pub(super) trash_emptying: Option<u64>, // 正在清空回收站, 序号不超过该值的文件需要清除 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) expiring_cursor: Option<String>, // 定时任务清除过期文件的进度 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) acl: BTreeMap<String, PathAcl>, // key 是路径前缀, 路径的访问控制 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) quotas: StorageQuotas, // 用户和角色的存储配额 // ? 堆内存 序列化
+    #[serde(default)]
+    pub(super) usage: HashMap<UserId, StorageUsage>, // 每个上传者的用量, 由文件索引, 历史版本和回收站维护 // ? 堆内存 序列化
+    #[serde(default)]
+    usage_indexed: bool, // 旧版本没有用量, 升级后需要从文件索引重建 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) next_token: u64, // 上传凭证的序号 // ? 堆内存 序列化
+    #[serde(default)]
+    pub(super) upload_tokens: HashMap<HashDigest, UploadTokenData>, // key 是凭证的 hash, 签发的上传凭证 // ? 堆内存 序列化
+
+    #[serde(default)]
+    pub(super) url_secret: Option<HashDigest>, // 签名链接的密钥, 第一次签名时生成 // ? 堆内存 序列化
+}
+
+impl Default for InnerState {
+    fn default() -> Self {
+        ic_cdk::println!("v002.InnerState::default()");
+        Self {
+            canister_kit: Default::default(),
+
+            // 业务数据
+            hashed: Default::default(),
+
+            assets: init_assets_index_data(),
+            files: init_files_data(),
+            hashes: init_hashes_data(),
+            versions: init_versions_data(),
+            trash: init_trash_data(),
+
+            uploading: Default::default(),
+            digesting: Default::default(),
+            digesters: Default::default(),
+
+            committing: Default::default(),
+
+            next_session: Default::default(),
+            uploading_timeout: DEFAULT_UPLOADING_TIMEOUT.into(),
+
+            challenges: Default::default(),
+
+            verifying: Default::default(),
+            verifier: None,
+
+            deleting: Default::default(),
+            purging: None,
+
+            version_retention: Default::default(),
+            versions_cursor: None,
+
+            next_trash: Default::default(),
+            trash_retention: default_trash_retention(),
+            trash_emptying: None,
+
+            expiring_cursor: None,
+
+            acl: Default::default(),
+
+            quotas: Default::default(),
+            usage: Default::default(),
+            usage_indexed: true, // 新的罐子没有文件
+
+            next_token: Default::default(),
+            upload_tokens: Default::default(),
+
+            url_secret: None,
+        }
+    }
+}
+
+impl InnerState {
+    pub fn do_init(&mut self, arg: InitArg) {
+        if let Some(uploading_timeout) = arg.uploading_timeout {
+            self.uploading_timeout = uploading_timeout;
+        }
+        if let Some(trash_retention) = arg.trash_retention {
+            self.trash_retention = trash_retention;
+        }
+    }
+
+    pub fn do_upgrade(&mut self, arg: UpgradeArg) {
+        if let Some(uploading_timeout) = arg.uploading_timeout {
+            self.uploading_timeout = uploading_timeout;
+        }
+        if let Some(trash_retention) = arg.trash_retention {
+            self.trash_retention = trash_retention;
+        }
+    }
+
+    fn put_file_at(
+        &mut self,
+        path: String,
+        headers: Vec<(String, String)>,
+        hash: HashDigest,
+        size: u64,
+        now: TimestampNanos,
+        uploader: Option<UserId>,
+    ) {
+        // 3. 插入 files: path -> hash, 数据还没有写完的文件暂不可访问
+        let status = self.hash_status(&hash);
+        if let Some(mut exist) = self.files.get(&path) {
+            let (old_hash, old_size) = (exist.hash, exist.size);
+            if old_hash != hash || exist.headers != headers {
+                self.archive_file(&exist, now); // 被覆盖的内容保存为历史版本
+            }
+            exist.modified = now;
+            exist.uploader = uploader;
+            exist.headers = headers;
+            exist.hash = hash;
+            exist.size = size;
+            exist.status = status;
+            self.insert_file(path.clone(), exist);
+            if old_hash != hash {
+                self.release_hash(&old_hash, old_size, &path); // 覆盖了原来的内容，需要释放原来的数据
+            }
+        } else {
+            self.insert_file(
+                path.clone(),
+                AssetFile {
+                    path: path.clone(),
+                    created: now,
+                    modified: now,
+                    headers,
+                    hash,
+                    size,
+                    status,
+                    uploader,
+                    expires_at: None,
+                },
+            );
+        }
+
+        // 4. 插入 hashes: hash -> [path]
+        self.put_hash(hash, path);
+    }
+    // 文件索引的变更都要经过这里, 同时维护上传者的用量
+    fn insert_file(&mut self, path: String, file: AssetFile) -> Option<AssetFile> {
+        let (uploader, size) = (file.uploader, file.size);
+        let old = self.files.insert(path, file);
+        if let Some(old) = &old {
+            self.usage_sub(old.uploader, old.size);
+        }
+        self.usage_add(uploader, size);
+        old
+    }
+    fn remove_file(&mut self, path: &String) -> Option<AssetFile> {
+        let old = self.files.remove(path);
+        if let Some(old) = &old {
+            self.usage_sub(old.uploader, old.size);
+        }
+        old
+    }
+    fn hash_status(&self, hash: &HashDigest) -> AssetStatus {
+        if self.assets.contains_key(hash) {
+            AssetStatus::Committed
+        } else {
+            AssetStatus::Committing
+        }
+    }
+    pub(super) fn put_hash(&mut self, hash: HashDigest, path: String) {
+        let mut hash_path = self.hashes.get(&hash).unwrap_or_default();
+        hash_path.0.insert(path);
+        self.hashes.insert(hash, hash_path);
+    }
+    fn put_assets(&mut self, path: &String) {
+        let Some(file) = self.uploading.get(path) else {
+            return;
+        };
+        // 1. 计算 hash
+        if self.hashed && !self.assets.contains_key(&file.hash) && !self.committing.contains_key(&file.hash) {
+            let hash = file.hash; // hashed true 并且是新的数据, 直接使用, 后台再校验
+            if let Some(file) = self.uploading.remove(path) {
+                self.put_assets_by(file, hash, false);
+            }
+        } else {
+            // hashed false 要计算一次
+            // 已经存在的数据也要计算一次, 否则只知道 hash 就能引用别人的数据
+            self.digest_uploading(path, false);
+        }
+    }
+    // 计算提交的暂存数据的 hash, 数据较多时在后续的消息中继续计算, 返回 None 表示还没有计算完
+    fn digest_uploading(&mut self, path: &String, checked: bool) -> Option<Result<(), StorageError>> {
+        self.digesting.insert(path.clone(), checked);
+        let mut budget = MAX_DIGEST_SIZE; // 本次消息的计算额度
+        self.digest_step(path, &mut budget)
+    }
+    fn digest_step(&mut self, path: &String, budget: &mut u64) -> Option<Result<(), StorageError>> {
+        let Some(file) = self.uploading.get(path) else {
+            self.forget_digesting(path);
+            return Some(Err(StorageError::NotFound(path.clone())));
+        };
+        let mut digester = self.digesters.remove(path).unwrap_or_default();
+        if !digester.step(file, budget) {
+            self.digesters.insert(path.clone(), digester);
+            return None;
+        }
+        let checked = self.digesting.remove(path).unwrap_or_default();
+        let (hash, size) = digester.finish();
+        // 服务端校验实际的长度和 hash, 校验失败保留上传会话, 可以重新上传数据块
+        let failed = if checked && size != file.size {
+            Some(StorageError::SizeMismatch {
+                expected: file.size,
+                actual: size,
+            })
+        } else if checked && hash != file.hash {
+            Some(StorageError::HashMismatch {
+                expected: file.hash.hex(),
+                actual: hash.hex(),
+            })
+        } else {
+            None
+        };
+        if let Some(err) = failed {
+            if let Some(file) = self.uploading.get_mut(path) {
+                file.failed = Some(err.clone()); // 后台校验失败时, 上传者可以从上传进度中看到
+            }
+            return Some(Err(err));
+        }
+        if let Some(file) = self.uploading.remove(path) {
+            self.put_assets_by(file, hash, true);
+        }
+        Some(Ok(()))
+    }
+    fn forget_digesting(&mut self, path: &String) {
+        self.digesting.remove(path);
+        self.digesters.remove(path);
+    }
+    fn put_assets_by(&mut self, file: UploadingFile, hash: HashDigest, verified: bool) {
+        let (path, headers, size, expires_at) = (file.path.clone(), file.headers.clone(), file.size, file.expires_at);
+        let uploader = file.uploader.unwrap_or_else(ic_canister_kit::identity::caller); // 旧版本的上传没有记录上传者
+        // 已有的数据没有校验过, 用校验过的数据替换
+        if verified && self.verifying.contains(&hash) {
+            self.drop_assets(&hash);
+        }
+        // 2. 等待写入 assets: hash -> data, 写入完成后才会插入 assets
+        if self.assets.contains_key(&hash) || self.committing.contains_key(&hash) {
+            file.remove(); // 已经有相同的数据了, 暂存的数据块不再需要
+        } else {
+            self.committing.insert(hash, AssetCommit::new(file));
+            if !verified {
+                self.verifying.insert(hash); // 等待后台校验
+            }
+        }
+
+        let now = ic_canister_kit::times::now();
+        self.put_file_at(path.clone(), headers, hash, size, now, Some(uploader)); // 登记完 assets 数据了，然后要对文件建立代理索引, 同路径的旧数据也会被释放
+        self.set_expires_at(&path, expires_at); // 上传的文件使用本次指定的过期时间
+    }
+    fn set_expires_at(&mut self, path: &String, expires_at: Option<TimestampNanos>) {
+        if let Some(mut file) = self.files.get(path)
+            && file.expires_at != expires_at
+        {
+            file.expires_at = expires_at;
+            self.insert_file(path.clone(), file);
+        }
+    }
+    // 清除 hash 对应的未校验数据, 引用该数据的文件没有证明持有数据, 需要重新上传或者证明持有后才能访问
+    fn drop_assets(&mut self, hash: &HashDigest) {
+        let size = self.hash_size(hash);
+        self.assets.remove(hash);
+        if let Some(commit) = self.committing.remove(hash) {
+            commit.file.remove(); // 还没有写完的数据, 暂存的数据块也要删除
+        }
+        if let Some(size) = size {
+            AssetData::remove(hash, size);
+        }
+        self.forget_verifying(hash);
+        if let Some(HashedPath(path_set)) = self.hashes.get(hash) {
+            for reference in path_set {
+                if let Some(mut file) = self.files.get(&reference) {
+                    file.status = AssetStatus::Mismatched;
+                    self.insert_file(reference, file);
+                } else {
+                    self.unlink_reference(hash, &reference); // 历史版本和回收站中的引用直接清除
+                }
+            }
+        }
+    }
+    // 清除 hash 对应的数据及所有引用, 返回被删除的文件
+    fn unlink_assets(&mut self, hash: &HashDigest) -> Vec<String> {
+        let size = self.hash_size(hash);
+        let mut removed = Vec::new();
+        if let Some(HashedPath(path_set)) = self.hashes.remove(hash) {
+            for reference in path_set {
+                if self.remove_file(&reference).is_some() {
+                    removed.push(reference); // 历史版本保留, 指向其他数据
+                } else {
+                    self.unlink_reference(hash, &reference);
+                }
+            }
+        }
+        self.assets.remove(hash);
+        if let Some(commit) = self.committing.remove(hash) {
+            commit.file.remove();
+        }
+        if let Some(size) = size {
+            AssetData::remove(hash, size);
+        }
+        self.forget_verifying(hash);
+        removed
+    }
+    // 清除历史版本或者回收站中的引用
+    fn unlink_reference(&mut self, hash: &HashDigest, reference: &str) {
+        if let Some(HashedPath(mut path_set)) = self.hashes.get(hash)
+            && path_set.remove(reference)
+        {
+            self.hashes.insert(*hash, HashedPath(path_set));
+        }
+        if let Some(id) = reference.strip_prefix("trash:") {
+            if let Some(trashed) = id.parse().ok().and_then(|id| self.trash.remove(&id)) {
+                self.usage_sub(trashed.file.uploader, trashed.file.size); // 见 trash_ref
+            }
+            return;
+        }
+        if let Some((version, path)) = reference.split_once(':')
+            && let Ok(version) = version.parse::<u64>()
+            && let Some(mut versions) = self.versions.get(&path.to_string())
+            && let Some(index) = versions.list.iter().position(|v| v.version == version)
+        {
+            let removed = versions.list.remove(index); // 见 version_ref
+            self.usage_sub(removed.uploader, removed.size);
+            self.versions.insert(path.to_string(), versions);
+        }
+    }
+    fn forget_verifying(&mut self, hash: &HashDigest) {
+        self.verifying.remove(hash);
+        if self.verifier.as_ref().is_some_and(|verifier| verifier.hash == *hash) {
+            self.verifier = None;
+        }
+    }
+    // 数据的长度, 从引用该数据的文件, 历史版本或者回收站中查找
+    fn hash_size(&self, hash: &HashDigest) -> Option<u64> {
+        let HashedPath(path_set) = self.hashes.get(hash)?;
+        path_set.iter().find_map(|reference| self.reference_size(reference))
+    }
+    fn reference_size(&self, reference: &String) -> Option<u64> {
+        if reference.starts_with('/') {
+            return self.files.get(reference).map(|file| file.size);
+        }
+        if let Some(id) = reference.strip_prefix("trash:") {
+            if let Some((id, version)) = id.split_once(':') {
+                let trashed = self.trash.get(&id.parse().ok()?)?; // 见 trash_version_ref
+                return trashed.versions.get(version.parse().ok()?).map(|version| version.size);
+            }
+            return self.trash.get(&id.parse().ok()?).map(|trashed| trashed.file.size); // 见 trash_ref
+        }
+        let (version, path) = reference.split_once(':')?; // 见 version_ref
+        let versions = self.versions.get(&path.to_string())?;
+        versions.get(version.parse().ok()?).map(|version| version.size)
+    }
+    pub fn clean_file(&mut self, path: &String) {
+        // 1. 删除文件
+        let file = match self.remove_file(path) {
+            Some(file) => file,
+            None => return,
+        };
+        // 2. 清除 hashes
+        self.release_hash(&file.hash, file.size, &file.path);
+        // 3. 清除历史版本
+        if let Some(versions) = self.versions.remove(path) {
+            self.release_versions(path, versions.list);
+        }
+    }
+    fn release_hash(&mut self, hash: &HashDigest, size: u64, path: &str) {
+        if let Some(HashedPath(mut path_set)) = self.hashes.get(hash) {
+            path_set.remove(path);
+            if !path_set.is_empty() {
+                self.hashes.insert(*hash, HashedPath(path_set));
+            } else {
+                // 需要清空
+                self.hashes.remove(hash);
+                // 4. 清空 assets
+                self.assets.remove(hash);
+                if let Some(commit) = self.committing.remove(hash) {
+                    commit.file.remove(); // 还没有写完的数据, 暂存的数据块也要删除
+                }
+                self.forget_verifying(hash);
+                // 5. 清空稳定内存中的数据块
+                AssetData::remove(hash, size);
+            }
+        }
+    }
+    // 被覆盖的内容保存为历史版本, 引用的数据不会被释放
+    fn archive_file(&mut self, file: &AssetFile, now: TimestampNanos) {
+        if self.version_retention.max_versions == 0 || file.status == AssetStatus::Mismatched {
+            return;
+        }
+        let mut versions = self.versions.get(&file.path).unwrap_or_default();
+        versions.next += 1;
+        self.put_hash(file.hash, version_ref(&file.path, versions.next));
+        versions.list.push(FileVersion {
+            version: versions.next,
+            hash: file.hash,
+            size: file.size,
+            headers: file.headers.clone(),
+            modified: file.modified,
+            uploader: file.uploader,
+            archived: now,
+        });
+        self.usage_add(file.uploader, file.size); // 历史版本也计入上传者的用量
+        let removed = versions.retain(&self.version_retention, now);
+        self.versions.insert(file.path.clone(), versions);
+        self.release_versions(&file.path, removed);
+    }
+    // 历史版本排在路径已有的版本之后, 没有已有版本时版本号不变
+    fn append_versions(&mut self, path: &str, versions: FileVersions) -> FileVersions {
+        let mut merged = self.versions.get(&path.to_string()).unwrap_or_default();
+        let offset = merged.next;
+        for mut version in versions.list {
+            version.version += offset;
+            self.put_hash(version.hash, version_ref(path, version.version));
+            merged.list.push(version);
+        }
+        merged.next = offset + versions.next;
+        merged
+    }
+    fn release_versions(&mut self, path: &str, versions: Vec<FileVersion>) {
+        for version in versions {
+            self.usage_sub(version.uploader, version.size);
+            self.release_hash(&version.hash, version.size, &version_ref(path, version.version));
+        }
+    }
+    // 开始清除没有被引用的数据块, 已经在清除时继续之前的进度
+    pub fn purge_orphans(&mut self) -> u64 {
+        self.purging.get_or_insert_default();
+        self.purge_step()
+    }
+    pub fn purging(&self) -> bool {
+        self.purging.is_some()
+    }
+    // 清除一批, 返回本批删除的块数
+    pub fn purge_step(&mut self) -> u64 {
+        let Some(purging) = self.purging.take() else {
+            return 0;
+        };
+        let (purged, cursor) = AssetData::purge(purging.cursor.as_deref(), MAX_PURGE_BUCKETS as usize, |hash| {
+            self.assets.contains_key(hash) || self.committing.contains_key(hash)
+        });
+        self.purging = cursor.map(|cursor| OrphanPurge { cursor: Some(cursor) });
+        purged
+    }
+    pub fn committing(&self) -> bool {
+        !self.committing.is_empty()
+    }
+    pub fn commit(&mut self) -> Vec<String> {
+        let mut budget = MAX_COMMIT_BUCKETS; // 本次消息的写入额度
+        let mut committed = Vec::new();
+        let hashes = self.committing.keys().copied().collect::<Vec<_>>();
+        for hash in hashes {
+            if budget == 0 {
+                break;
+            }
+            let (done, size) = match self.committing.get_mut(&hash) {
+                Some(commit) => (commit.step(&hash, &mut budget), commit.file.size),
+                None => continue,
+            };
+            if !done {
+                continue;
+            }
+            // 全部写入完成, 可以对外访问了
+            self.committing.remove(&hash);
+            self.assets.insert(hash, AssetData {});
+            if let Some(HashedPath(path_set)) = self.hashes.get(&hash) {
+                for path in path_set {
+                    // 没有证明持有数据的文件不能恢复访问
+                    if let Some(mut file) = self
+                        .files
+                        .get(&path)
+                        .filter(|file| file.status == AssetStatus::Committing)
+                    {
+                        file.status = AssetStatus::Committed;
+                        file.size = size; // 替换过的数据, 长度以写入的数据为准
+                        self.insert_file(path.clone(), file);
+                        committed.push(path);
+                    }
+                }
+            }
+        }
+        committed
+    }
+    pub fn files(&self, caller: &UserId) -> Vec<QueryFile> {
+        self.files
+            .values()
+            .filter(|file| self.readable(&file.path, caller))
+            .map(QueryFile::from)
+            .collect()
+    }
+    pub fn files_by_page(
+        &self,
+        page: &QueryPage,
+        prefix: String,
+        cursor: Option<String>,
+        sort: FileSort,
+        caller: &UserId,
+    ) -> Result<QueryFilePage, StorageError> {
+        use std::ops::Bound;
+        page.check(MAX_FILES_PAGE_SIZE)
+            .map_err(|e| StorageError::InvalidArgument(e.to_string()))?;
+
+        // 1. 前缀匹配的总数, 按游标翻页时不统计, 避免每页都遍历整个前缀
+        let total = match cursor {
+            Some(_) => 0,
+            None => self
+                .files
+                .keys_range(prefix.clone()..)
+                .take_while(|path| path.starts_with(&prefix))
+                .filter(|path| self.readable(path, caller))
+                .count() as u64,
+        };
+
+        // 2. 有游标时从游标之后开始, 否则按页码跳过
+        let skip = match cursor {
+            Some(_) => 0,
+            None => ((page.page - 1) * page.size as u64) as usize,
+        };
+        let take = page.size as usize + 1; // 多取一个, 判断是否还有下一页
+        let upper = Self::prefix_upper(&prefix); // 前缀之后的第一个键, 不扫描前缀之外的文件
+        let mut data = match sort {
+            FileSort::PathAsc => {
+                let start = match cursor {
+                    Some(cursor) if prefix <= cursor => Bound::Excluded(cursor),
+                    _ => Bound::Included(prefix.clone()),
+                };
+                self.files
+                    .range((start, upper))
+                    .filter(|entry| self.readable(entry.key(), caller))
+                    .skip(skip)
+                    .take(take)
+                    .map(|entry| entry.value())
+                    .collect::<Vec<_>>()
+            }
+            FileSort::PathDesc => {
+                let end = match (cursor, upper) {
+                    (Some(cursor), _) if cursor <= prefix => {
+                        return Ok(QueryFilePage {
+                            page: page.from_data(total, vec![]),
+                            next: None,
+                        });
+                    } // 游标之前没有匹配的文件
+                    (Some(cursor), Bound::Excluded(upper)) if upper < cursor => Bound::Excluded(upper),
+                    (Some(cursor), _) => Bound::Excluded(cursor),
+                    (None, upper) => upper,
+                };
+                self.files
+                    .range((Bound::Included(prefix.clone()), end))
+                    .rev()
+                    .filter(|entry| self.readable(entry.key(), caller))
+                    .skip(skip)
+                    .take(take)
+                    .map(|entry| entry.value())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // 3. 下一页的游标
+        let mut next = None;
+        if (page.size as usize) < data.len() {
+            data.pop();
+            next = data.last().map(|file| file.path.clone());
+        }
+
+        Ok(QueryFilePage {
+            page: page.from_data(total, data.into_iter().map(QueryFile::from).collect()),
+            next,
+        })
+    }
+    // 以 prefix 开头的键的上界, 最后一个字符加一; 全是最大字符时没有上界
+    fn prefix_upper(prefix: &str) -> std::ops::Bound<String> {
+        let mut chars = prefix.chars().collect::<Vec<_>>();
+        while let Some(c) = chars.pop() {
+            if let Some(c) = (c as u32 + 1..=char::MAX as u32).find_map(char::from_u32) {
+                chars.push(c);
+                return std::ops::Bound::Excluded(chars.into_iter().collect());
+            }
+        }
+        std::ops::Bound::Unbounded
+    }
+    // 目录路径统一以 / 结尾
+    fn dir_prefix(path: &str) -> Result<String, StorageError> {
+        if !path.starts_with('/') {
+            return Err(StorageError::InvalidPath("path must start with /".into()));
+        }
+        if path.ends_with('/') {
+            return Ok(path.to_string());
+        }
+        Ok(format!("{path}/"))
+    }
+    pub fn list_dir(
+        &self,
+        path: String,
+        cursor: Option<String>,
+        limit: u32,
+        caller: &UserId,
+    ) -> Result<QueryDir, StorageError> {
+        use std::ops::Bound;
+        let prefix = Self::dir_prefix(&path)?;
+        if limit == 0 || MAX_FILES_PAGE_SIZE < limit {
+            return Err(StorageError::InvalidArgument(format!(
+                "limit must be between 1 and {MAX_FILES_PAGE_SIZE}"
+            )));
+        }
+
+        // 1. 游标是上一页最后的文件路径, 或者以 / 结尾的子目录
+        let mut start = match cursor {
+            Some(cursor) if cursor.starts_with(&prefix) && prefix.len() < cursor.len() => {
+                match cursor.strip_suffix('/') {
+                    Some(dir) => Bound::Included(format!("{dir}0")), // '0' 紧跟在 '/' 之后, 跳过整个子目录
+                    None => Bound::Excluded(cursor),
+                }
+            }
+            _ => Bound::Included(prefix.clone()),
+        };
+
+        // 2. 遇到子目录时记下名字, 然后跳过整个子树, 不向下遍历
+        let (mut dirs, mut files) = (vec![], vec![]);
+        let mut last = None;
+        let mut next = None;
+        'scan: loop {
+            let mut skip = None;
+            for entry in self
+                .files
+                .range((start.clone(), Bound::Unbounded))
+                .take_while(|entry| entry.key().starts_with(&prefix))
+                .filter(|entry| self.readable(entry.key(), caller))
+            {
+                if dirs.len() + files.len() == limit as usize {
+                    next = last; // 还有更多子项
+                    break 'scan;
+                }
+                match entry.key()[prefix.len()..].split_once('/') {
+                    Some((name, _)) => {
+                        dirs.push(QueryDirEntry { name: name.to_string() });
+                        last = Some(format!("{prefix}{name}/"));
+                        skip = Some(format!("{prefix}{name}0"));
+                        break;
+                    }
+                    None => {
+                        last = Some(entry.key().clone());
+                        files.push(QueryFile::from(entry.value()));
+                    }
+                }
+            }
+            match skip {
+                Some(key) => start = Bound::Included(key),
+                None => break,
+            }
+        }
+
+        Ok(QueryDir {
+            path: prefix,
+            dirs,
+            files,
+            next,
+        })
+    }
+    pub fn files_after(&self, path: Option<String>, limit: usize) -> Vec<AssetFile> {
+        use std::ops::Bound;
+        let start = path.map_or(Bound::Unbounded, Bound::Excluded);
+        self.files
+            .range((start, Bound::Unbounded))
+            .take(limit)
+            .map(|entry| entry.value())
+            .collect()
+    }
+    fn find_asset(
+        &self,
+        path: String,
+        version: Option<u64>,
+        now: TimestampNanos,
+    ) -> Result<(AssetFile, AssetData), StorageError> {
+        let file = match version {
+            Some(version) => self.version_file(&path, version),
+            None => self.files.get(&path),
+        };
+        let file = file
+            .filter(|file| file.status == AssetStatus::Committed) // 还在写入或者需要证明持有的数据不可访问
+            .filter(|file| !file.expired(now)) // 过期的文件不可访问
+            .ok_or_else(|| StorageError::NotFound(path.clone()))?;
+        let asset = self.assets.get(&file.hash).ok_or(StorageError::NotFound(path))?;
+        Ok((file, asset))
+    }
+    pub fn download(&self, path: String, now: TimestampNanos) -> Result<Vec<u8>, StorageError> {
+        let (file, asset) = self.find_asset(path, None, now)?;
+        Ok(asset.slice(&file.hash, file.size, 0, file.size as usize).to_vec())
+    }
+    pub fn download_by(
+        &self,
+        path: String,
+        offset: u64,
+        size: u64,
+        version: Option<u64>,
+        now: TimestampNanos,
+    ) -> Result<Vec<u8>, StorageError> {
+        let (file, asset) = self.find_asset(path, version, now)?;
+        if file.size <= offset || file.size - offset < size {
+            return Err(StorageError::OutOfRange {
+                offset,
+                size,
+                total: file.size,
+            });
+        }
+        Ok(asset
+            .slice(&file.hash, file.size, offset as usize, size as usize)
+            .to_vec())
+    }
+
+    fn chunks(size: u64, chunk_size: u32) -> u32 {
+        size.div_ceil(chunk_size as u64) as u32 // 需要上传的块数
+    }
+    fn check_path_and_headers(path: &str, headers: &[(String, String)]) -> Result<(), StorageError> {
+        // 1. 检查 路径名
+        if path.is_empty() {
+            return Err(StorageError::InvalidPath("must has path".into()));
+        }
+        if !path.starts_with('/') {
+            return Err(StorageError::InvalidPath("path must start with /".into()));
+        }
+        // 2. 检查 headers
+        for (name, value) in headers {
+            if 64 < name.len() || 1024 * 8 < value.len() {
+                return Err(StorageError::InvalidHeader(name.chars().take(64).collect()));
+            }
+        }
+        Ok(())
+    }
+    fn check_file_size(size: u64) -> Result<(), StorageError> {
+        if size == 0 {
+            return Err(StorageError::InvalidArgument("size can not be 0".into()));
+        }
+        if MAX_FILE_SIZE < size {
+            return Err(StorageError::TooLarge {
+                size,
+                max: MAX_FILE_SIZE,
+            });
+        }
+        Ok(())
+    }
+    fn check_size(size: u64, chunk_size: u32) -> Result<(), StorageError> {
+        // 3. 检查 size
+        Self::check_file_size(size)?;
+        // 4. 检查 chunk_size
+        if chunk_size == 0 {
+            return Err(StorageError::InvalidArgument("chunk size can not be 0".into()));
+        }
+        if chunk_size < MIN_CHUNK_SIZE && (chunk_size as u64) < size {
+            return Err(StorageError::InvalidArgument(format!(
+                "chunk size must be at least {MIN_CHUNK_SIZE} unless the file has only one chunk"
+            )));
+        }
+        Ok(())
+    }
+    // 上传者自己的会话, 其他用户的会话视为不存在, 旧版本没有记录上传者的除外
+    fn owned_session_path(&self, session: u64, caller: &UserId) -> Result<String, StorageError> {
+        self.session_path(session)
+            .filter(|path| {
+                self.uploading
+                    .get(path)
+                    .is_some_and(|file| file.uploader.is_none_or(|uploader| uploader == *caller))
+            })
+            .ok_or(StorageError::SessionNotFound(session))
+    }
+    fn session_path(&self, session: u64) -> Option<String> {
+        self.uploading
+            .values()
+            .find(|file| file.session == session)
+            .map(|file| file.path.clone())
+    }
+    fn assure_uploading(&mut self, arg: &UploadBeginArg, uploader: UserId) -> Result<u64, StorageError> {
+        let now = ic_canister_kit::times::now();
+        if arg
+            .expires_at
+            .is_some_and(|expires_at| expires_at.into_inner() <= now.into_inner())
+        {
+            return Err(StorageError::InvalidArgument("expires at must be in the future".into()));
+        }
+        self.check_quota(&uploader, &[(&arg.path, arg.size)])?; // 暂存数据前先检查配额
+        if self.digesting.contains_key(&arg.path) {
+            return Err(StorageError::Committing(arg.path.clone())); // 已经提交还在计算 hash 的数据不能修改
+        }
+        let chunks = Self::chunks(arg.size, arg.chunk_size);
+        if let Some(exist) = self.uploading.get_mut(&arg.path) {
+            // 已经有这个文件了, 需要比较一下, 参数是否一致
+            assert!(exist.path == arg.path, "wrong path, system error.");
+            if exist.hash == arg.hash
+                && exist.size == arg.size
+                && exist.chunk_size == arg.chunk_size
+                && exist.chunks == chunks
+                && exist.chunked.len() == chunks as usize
+            {
+                exist.headers = arg.headers.clone();
+                exist.expires_at = arg.expires_at;
+                exist.uploader = Some(uploader);
+                exist.touched = now;
+                return Ok(exist.session); // 参数一致, 继续上传
+            }
+            // 非致命错误, 清空原来暂存的数据, 重新开始上传
+            exist.remove();
+            self.uploading.remove(&arg.path);
+        }
+        // 暂存的数据块以路径为键, 该路径上次上传的数据还没有写完时, 不能覆盖
+        if self.committing.values().any(|commit| commit.file.path == arg.path) {
+            return Err(StorageError::Committing(arg.path.clone()));
+        }
+        // 原来没有的情况下
+        self.next_session += 1;
+        let session = self.next_session;
+        self.uploading.insert(
+            arg.path.clone(),
+            UploadingFile {
+                session,
+                path: arg.path.clone(),
+                headers: arg.headers.clone(),
+                hash: arg.hash,
+                size: arg.size,
+                chunk_size: arg.chunk_size,
+                chunks,
+                chunked: vec![false; chunks as usize],
+                created: now,
+                touched: now,
+                expires_at: arg.expires_at,
+                uploader: Some(uploader),
+                failed: None,
+            },
+        );
+        Ok(session)
+    }
+    pub fn put_uploading(&mut self, arg: UploadingArg) -> Result<(), StorageError> {
+        self.put_uploading_by(arg, ic_canister_kit::identity::caller())
+    }
+    // 指定上传者, 上传完成的文件和配额都记在上传者名下
+    fn put_uploading_by(&mut self, arg: UploadingArg, uploader: UserId) -> Result<(), StorageError> {
+        // 1. 检查参数是否有效
+        Self::check_path_and_headers(&arg.path, &arg.headers)?;
+
+        // 2. 已经存在的数据也需要完整上传, 不上传数据需要先证明持有该数据
+
+        // 3. 检查其他参数
+        Self::check_size(arg.size, arg.chunk_size)?;
+
+        // 4. 确保有缓存空间
+        self.assure_uploading(
+            &UploadBeginArg {
+                path: arg.path.clone(),
+                headers: arg.headers,
+                hash: arg.hash,
+                size: arg.size,
+                chunk_size: arg.chunk_size,
+                expires_at: arg.expires_at,
+            },
+            uploader,
+        )?; // 确保该文件已经存在缓存数据了
+
+        // 5. 找的对应的缓存文件
+        let mut done = false;
+        if let Some(file) = self.uploading.get_mut(&arg.path) {
+            file.put(arg.index, arg.chunk)?; // 写入稳定内存
+            file.touched = ic_canister_kit::times::now();
+            done = file.chunked.iter().all(|c| *c); // 是否已经完整
+        }
+        if done {
+            // 处理这个已经完成的数据
+            self.put_assets(&arg.path);
+        }
+        Ok(())
+    }
+    pub fn clean_uploading(&mut self, path: &String) {
+        self.forget_digesting(path);
+        if let Some(file) = self.uploading.remove(path) {
+            file.remove(); // 删除暂存的数据块
+        }
+    }
+
+    // ========== 上传会话 ==========
+
+    pub fn upload_begin(&mut self, arg: UploadBeginArg) -> Result<u64, StorageError> {
+        Self::check_path_and_headers(&arg.path, &arg.headers)?;
+        Self::check_size(arg.size, arg.chunk_size)?;
+        self.assure_uploading(&arg, ic_canister_kit::identity::caller())
+    }
+    pub fn upload_put(&mut self, arg: UploadChunkArg, caller: &UserId) -> Result<(), StorageError> {
+        let path = self.owned_session_path(arg.session, caller)?;
+        if self.digesting.contains_key(&path) {
+            return Err(StorageError::Committing(path)); // 已经提交的数据不能再修改
+        }
+        if let Some(file) = self.uploading.get_mut(&path) {
+            file.put(arg.index, arg.chunk)?;
+            file.touched = ic_canister_kit::times::now();
+            file.failed = None; // 重新上传了数据块, 上次的校验结果不再有效
+        }
+        Ok(())
+    }
+    pub fn upload_status(&self, session: u64) -> Option<UploadingStatus> {
+        let path = self.session_path(session)?;
+        let mut status = self.uploading.get(&path)?.status();
+        status.committing = self.digesting.contains_key(&path);
+        Some(status)
+    }
+    // 数据较多时在后台继续校验, 返回等待中, 校验失败的结果可以从上传进度中看到
+    pub fn upload_commit(&mut self, session: u64, caller: &UserId) -> Result<UploadCommitted, StorageError> {
+        let path = self.owned_session_path(session, caller)?;
+        let file = self
+            .uploading
+            .get(&path)
+            .ok_or(StorageError::SessionNotFound(session))?;
+        let uploaded = file.chunked.iter().filter(|c| **c).count() as u32;
+        if uploaded < file.chunks {
+            return Err(StorageError::UploadNotFinished {
+                uploaded,
+                chunks: file.chunks,
+            });
+        }
+
+        if self.digesting.contains_key(&path) {
+            return Err(StorageError::Committing(path));
+        }
+
+        // 服务端校验实际的长度和 hash, 没有计算完的数据在后台继续校验
+        match self.digest_uploading(&path, true) {
+            Some(result) => result.map(|_| UploadCommitted::Committed(path)),
+            None => Ok(UploadCommitted::Pending(path)),
+        }
+    }
+    pub fn upload_abort(&mut self, session: u64, caller: &UserId) -> Result<(), StorageError> {
+        let path = self.owned_session_path(session, caller)?;
+        self.clean_uploading(&path);
+        Ok(())
+    }
+    pub fn upload_expire(&mut self, now: TimestampNanos) -> Vec<(u64, String)> {
+        for file in self
+            .uploading
+            .values_mut()
+            .filter(|file| file.touched.into_inner() == 0)
+        {
+            file.created = now; // 旧版本迁移来的上传没有记录时间, 从第一次检查开始计算
+            file.touched = now;
+        }
+        let expired = self
+            .uploading
+            .values()
+            .filter(|file| file.expired(now, self.uploading_timeout) && !self.digesting.contains_key(&file.path))
+            .map(|file| (file.session, file.path.clone()))
+            .collect::<Vec<_>>();
+        for (_, path) in expired.iter() {
+            self.clean_uploading(path);
+        }
+        let timeout = self.uploading_timeout;
+        self.challenges.retain(|_, challenge| !challenge.expired(now, timeout)); // 过期的挑战也一并清除
+        self.upload_tokens.retain(|_, token| !token.expired(now)); // 过期的上传凭证也一并清除
+        expired
+    }
+
+    // ========== 上传凭证 ==========
+
+    pub fn upload_token_mint(
+        &mut self,
+        arg: UploadTokenArg,
+        random: [u8; 32],
+        now: TimestampNanos,
+        issuer: UserId,
+    ) -> Result<UploadToken, StorageError> {
+        Self::check_path_and_headers(&arg.prefix, &[])?;
+        let prefix = Self::dir_prefix(&arg.prefix)?; // 以 / 结尾, /u/user1 的凭证不能上传到 /u/user10 下
+        Self::check_file_size(arg.max_size)?;
+        if arg.expires_at.into_inner() <= now.into_inner() {
+            return Err(StorageError::InvalidArgument("expires at must be in the future".into()));
+        }
+        self.upload_tokens.retain(|_, token| !token.expired(now));
+        if MAX_UPLOAD_TOKENS <= self.upload_tokens.len() {
+            return Err(StorageError::InvalidArgument(format!(
+                "too many tokens, must less than {MAX_UPLOAD_TOKENS}"
+            )));
+        }
+        self.next_token += 1;
+        let id = self.next_token;
+        let token = HashDigest(random).hex();
+        self.upload_tokens.insert(
+            token_hash(&token),
+            UploadTokenData {
+                id,
+                prefix,
+                max_size: arg.max_size,
+                expires_at: arg.expires_at,
+                issuer,
+                path: None,
+            },
+        );
+        Ok(UploadToken { id, token })
+    }
+    pub fn upload_tokens(&self) -> Vec<QueryUploadToken> {
+        let mut tokens = self
+            .upload_tokens
+            .values()
+            .map(QueryUploadToken::from)
+            .collect::<Vec<_>>();
+        tokens.sort_by_key(|token| token.id);
+        tokens
+    }
+    pub fn upload_token_revoke(&mut self, id: u64) -> Result<(), StorageError> {
+        let count = self.upload_tokens.len();
+        self.upload_tokens.retain(|_, token| token.id != id);
+        if self.upload_tokens.len() == count {
+            return Err(StorageError::InvalidToken(format!("upload token not found: {id}")));
+        }
+        Ok(())
+    }
+    // 凭证只能上传一个文件, 上传完成后失效, 返回凭证的序号
+    pub fn upload_by_token(
+        &mut self,
+        token: &str,
+        args: Vec<UploadingArg>,
+        now: TimestampNanos,
+    ) -> Result<u64, StorageError> {
+        let key = token_hash(token);
+        let data = self
+            .upload_tokens
+            .get(&key)
+            .filter(|data| !data.expired(now))
+            .cloned()
+            .ok_or_else(|| StorageError::InvalidToken("upload token not found or expired".into()))?;
+        let path = match data.path.clone().or_else(|| args.first().map(|arg| arg.path.clone())) {
+            Some(path) => path,
+            None => return Ok(data.id),
+        };
+
+        // 1. 先检查, 出错时不写入任何数据
+        for arg in args.iter() {
+            if arg.path != path {
+                return Err(StorageError::InvalidToken(format!(
+                    "token can only upload one file: {path}"
+                )));
+            }
+            if !arg.path.starts_with(&data.prefix) {
+                return Err(StorageError::InvalidPath(format!(
+                    "path must start with {}",
+                    data.prefix
+                )));
+            }
+            if data.max_size < arg.size {
+                return Err(StorageError::TooLarge {
+                    size: arg.size,
+                    max: data.max_size,
+                });
+            }
+        }
+
+        // 2. 绑定路径, 上传的文件记在签发者名下
+        if let Some(token) = self.upload_tokens.get_mut(&key) {
+            token.path = Some(path.clone());
+        }
+        for arg in args {
+            self.put_uploading_by(arg, data.issuer)?; // 出错时, 之前的数据块已经写入
+        }
+
+        // 3. 上传完成后凭证失效
+        if !self.uploading.contains_key(&path) {
+            self.upload_tokens.remove(&key);
+        }
+        Ok(data.id)
+    }
+
+    // ========== 签名链接 ==========
+
+    pub fn url_secret_ready(&self) -> bool {
+        self.url_secret.is_some()
+    }
+    // 没有密钥时使用传入的随机数生成, 链接在有效时间内不受访问控制限制
+    pub fn sign_url(
+        &mut self,
+        path: String,
+        version: Option<u64>,
+        ttl: DurationNanos,
+        now: TimestampNanos,
+        random: Option<[u8; 32]>,
+    ) -> Result<SignedUrl, StorageError> {
+        if ttl.into_inner() == 0 || MAX_SIGNED_URL_TTL < ttl.into_inner() {
+            return Err(StorageError::InvalidArgument(format!(
+                "ttl must be between 1 and {MAX_SIGNED_URL_TTL}"
+            )));
+        }
+        self.find_asset(path.clone(), version, now)?;
+        if self.url_secret.is_none() {
+            self.url_secret = random.map(HashDigest);
+        }
+        let secret = self
+            .url_secret
+            .ok_or_else(|| StorageError::Internal("url secret is not ready".into()))?;
+        let exp = ((now.into_inner() as u128 + ttl.into_inner()) / 1_000_000_000) as u64; // 精确到秒
+        Ok(SignedUrl {
+            url: signed_url(&secret, &path, version, exp),
+            expires_at: ((exp as i128) * 1_000_000_000).into(),
+        })
+    }
+    pub fn url_verified(&self, path: &str, version: Option<u64>, sig: &str, exp: u64, now: TimestampNanos) -> bool {
+        self.url_secret
+            .as_ref()
+            .is_some_and(|secret| url_verified(secret, path, version, sig, exp, now))
+    }
+
+    // ========== 持有证明 ==========
+
+    pub fn upload_challenge(&mut self, hash: HashDigest, random: [u8; 32]) -> Option<UploadChallenge> {
+        if !self.assets.contains_key(&hash) {
+            return None; // 没有可以引用的数据, 需要完整上传
+        }
+        let data_size = self.hash_size(&hash)?;
+        // 随机选取一段数据
+        let size = std::cmp::min(MAX_CHALLENGE_SIZE, data_size);
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&random[..8]);
+        let offset = u64::from_be_bytes(seed) % (data_size - size + 1);
+        let nonce = HashDigest(random);
+        self.challenges.insert(
+            nonce,
+            UploadChallengeData {
+                hash,
+                offset,
+                size,
+                created: ic_canister_kit::times::now(),
+            },
+        );
+        Some(UploadChallenge { nonce, offset, size })
+    }
+    pub fn upload_prove(
+        &mut self,
+        arg: UploadProofArg,
+        now: TimestampNanos,
+        caller: UserId,
+    ) -> Result<(), StorageError> {
+        Self::check_path_and_headers(&arg.path, &arg.headers)?;
+        let challenge = self
+            .challenges
+            .remove(&arg.nonce)
+            .ok_or(StorageError::ChallengeNotFound)?; // 挑战只能使用一次
+        let asset = self
+            .assets
+            .get(&challenge.hash)
+            .ok_or_else(|| StorageError::NotFound(challenge.hash.hex()))?;
+        let size = self
+            .hash_size(&challenge.hash)
+            .ok_or_else(|| StorageError::NotFound(challenge.hash.hex()))?;
+
+        // 检查回答
+        let data = asset.slice(
+            &challenge.hash,
+            size,
+            challenge.offset as usize,
+            challenge.size as usize,
+        );
+        if UploadChallenge::answer(&arg.nonce, &data) != arg.proof {
+            return Err(StorageError::ProofMismatch);
+        }
+
+        self.check_quota(&caller, &[(&arg.path, size)])?; // 引用已有的数据也计入配额
+        self.put_file_at(arg.path, arg.headers, challenge.hash, size, now, Some(caller)); // size 不可信，只能从已存在的文件内容中查找
+        Ok(())
+    }
+
+    // ========== 批量修改 ==========
+
+    // 目录下的所有文件, 需要在一条消息内处理完
+    fn files_in_dir(&self, prefix: &str) -> Result<Vec<AssetFile>, StorageError> {
+        let files = self
+            .files
+            .range(prefix.to_string()..)
+            .take_while(|entry| entry.key().starts_with(prefix))
+            .take(MAX_BATCH_FILES as usize + 1)
+            .map(|entry| entry.value())
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return Err(StorageError::NotFound(prefix.to_string()));
+        }
+        if (MAX_BATCH_FILES as usize) < files.len() {
+            return Err(StorageError::InvalidArgument(format!(
+                "too many files, must less than {MAX_BATCH_FILES}"
+            )));
+        }
+        Ok(files)
+    }
+    // 以 / 结尾的路径表示更新目录下的所有文件
+    pub fn update_headers(
+        &mut self,
+        path: String,
+        headers: Vec<(String, String)>,
+        now: TimestampNanos,
+    ) -> Result<Vec<String>, StorageError> {
+        Self::check_path_and_headers(&path, &headers)?;
+        let files = if path.ends_with('/') {
+            self.files_in_dir(&path)?
+        } else {
+            vec![self.files.get(&path).ok_or(StorageError::NotFound(path))?]
+        };
+
+        let mut changed = Vec::with_capacity(files.len());
+        for mut file in files {
+            if file.headers != headers {
+                self.archive_file(&file, now); // 与覆盖上传一致, 原来的 headers 保存为历史版本
+            }
+            file.headers = headers.clone();
+            file.modified = now; // 内容不变, hash 不变
+            self.insert_file(file.path.clone(), file.clone());
+            changed.push(file.path);
+        }
+        Ok(changed)
+    }
+
+    // ========== 移动和复制 ==========
+
+    // 原文件和目标路径, 以 / 结尾的表示目录, 按前缀替换
+    fn transfer_pairs(&self, from: &str, to: &str) -> Result<Vec<(AssetFile, String)>, StorageError> {
+        if !to.starts_with('/') {
+            return Err(StorageError::InvalidPath("path must start with /".into()));
+        }
+        if !from.ends_with('/') {
+            if to.ends_with('/') {
+                return Err(StorageError::InvalidPath(format!("target must be a file: {to}")));
+            }
+            let file = self
+                .files
+                .get(&from.to_string())
+                .ok_or_else(|| StorageError::NotFound(from.to_string()))?;
+            if from == to {
+                return Err(StorageError::InvalidArgument(format!("same path: {to}")));
+            }
+            return Ok(vec![(file, to.to_string())]);
+        }
+
+        let to = Self::dir_prefix(to)?;
+        if to.starts_with(from) {
+            return Err(StorageError::InvalidPath(format!("target is inside the source: {to}")));
+        }
+        Ok(self
+            .files_in_dir(from)?
+            .into_iter()
+            .map(|file| {
+                let path = format!("{to}{}", &file.path[from.len()..]);
+                (file, path)
+            })
+            .collect())
+    }
+    pub fn move_files(
+        &mut self,
+        from: String,
+        to: String,
+        overwrite: bool,
+        now: TimestampNanos,
+    ) -> Result<Vec<String>, StorageError> {
+        let pairs = self.transfer_pairs(&from, &to)?;
+        let sources = pairs.iter().map(|(file, _)| file.path.clone()).collect::<HashSet<_>>();
+
+        // 1. 先检查, 出错时不修改任何数据
+        if !overwrite
+            && let Some((_, path)) = pairs
+                .iter()
+                .find(|(_, path)| !sources.contains(path) && self.files.contains_key(path))
+        {
+            return Err(StorageError::AlreadyExists(path.clone()));
+        }
+
+        // 2. 移除原路径, 历史版本跟随文件移动
+        let mut versions = Vec::new();
+        for (file, path) in &pairs {
+            self.remove_file(&file.path);
+            if let Some(list) = self.versions.remove(&file.path) {
+                versions.push((file.path.clone(), path.clone(), list));
+            }
+        }
+        // 3. 写入新路径, 和普通的覆盖一样, 被覆盖的文件保存为历史版本
+        for (file, path) in &pairs {
+            self.put_hash(file.hash, path.clone());
+            if let Some(old) = self.files.get(path)
+                && (old.hash != file.hash || old.headers != file.headers)
+            {
+                self.archive_file(&old, now);
+            }
+            let mut moved = file.clone();
+            moved.path = path.clone();
+            if let Some(old) = self.insert_file(path.clone(), moved)
+                && old.hash != file.hash
+            {
+                self.release_hash(&old.hash, old.size, path);
+            }
+        }
+        for (_, path, list) in &versions {
+            let mut merged = self.append_versions(path, list.clone());
+            let removed = merged.retain(&self.version_retention, now);
+            self.versions.insert(path.clone(), merged);
+            self.release_versions(path, removed);
+        }
+        // 4. 原路径释放引用, 原路径又写入了相同数据的除外
+        for (file, _) in &pairs {
+            if self.files.get(&file.path).is_none_or(|f| f.hash != file.hash) {
+                self.release_hash(&file.hash, file.size, &file.path);
+            }
+        }
+        for (from, _, list) in versions {
+            let current = self.versions.get(&from).unwrap_or_default();
+            for version in list.list {
+                if current.get(version.version).is_none_or(|v| v.hash != version.hash) {
+                    self.release_hash(&version.hash, version.size, &version_ref(&from, version.version)); // 用量跟随历史版本移动
+                }
+            }
+        }
+
+        let mut changed = sources.into_iter().collect::<Vec<_>>();
+        changed.extend(pairs.into_iter().map(|(_, path)| path));
+        Ok(changed)
+    }
+    // 复制出的文件属于复制的用户, 计入该用户的配额
+    pub fn copy_files(
+        &mut self,
+        from: String,
+        to: String,
+        uploader: Option<UserId>,
+    ) -> Result<Vec<String>, StorageError> {
+        let pairs = self.transfer_pairs(&from, &to)?;
+
+        // 1. 先检查, 复制不覆盖已有的文件
+        if let Some((_, path)) = pairs.iter().find(|(_, path)| self.files.contains_key(path)) {
+            return Err(StorageError::AlreadyExists(path.clone()));
+        }
+        if let Some(uploader) = &uploader {
+            let files = pairs
+                .iter()
+                .map(|(file, path)| (path.as_str(), file.size))
+                .collect::<Vec<_>>();
+            self.check_quota(uploader, &files)?;
+        }
+
+        // 2. 新路径指向相同的数据
+        let mut changed = Vec::with_capacity(pairs.len());
+        for (mut file, path) in pairs {
+            self.put_hash(file.hash, path.clone());
+            file.path = path.clone();
+            file.uploader = uploader;
+            self.insert_file(path.clone(), file);
+            changed.push(path);
+        }
+        Ok(changed)
+    }
+
+    // ========== 历史版本 ==========
+
+    pub fn file_versions(&self, path: String) -> Result<Vec<QueryFileVersion>, StorageError> {
+        if !self.files.contains_key(&path) {
+            return Err(StorageError::NotFound(path));
+        }
+        let versions = self.versions.get(&path).unwrap_or_default();
+        Ok(versions.list.into_iter().rev().map(QueryFileVersion::from).collect()) // 新的版本在前
+    }
+    // 历史版本对应的文件, 创建时间沿用当前文件
+    pub fn version_file(&self, path: &str, version: u64) -> Option<AssetFile> {
+        let versions = self.versions.get(&path.to_string())?;
+        let version = versions.get(version)?;
+        let current = self.files.get(&path.to_string());
+        let created = current.as_ref().map_or(version.modified, |file| file.created);
+        let mut file = version.to_file(path.to_string(), created, self.hash_status(&version.hash));
+        file.expires_at = current.and_then(|file| file.expires_at);
+        Some(file)
+    }
+    // 恢复历史版本, 当前的内容也会保存为历史版本
+    pub fn restore_version(
+        &mut self,
+        path: String,
+        version: u64,
+        now: TimestampNanos,
+        uploader: Option<UserId>,
+    ) -> Result<(), StorageError> {
+        if !self.files.contains_key(&path) {
+            return Err(StorageError::NotFound(path));
+        }
+        let restored = self
+            .versions
+            .get(&path)
+            .and_then(|versions| versions.get(version).cloned())
+            .ok_or_else(|| StorageError::NotFound(format!("{path}?version={version}")))?;
+        if let Some(uploader) = &uploader {
+            self.check_quota(uploader, &[(&path, restored.size)])?;
+        }
+        self.put_file_at(path, restored.headers, restored.hash, restored.size, now, uploader);
+        Ok(())
+    }
+    pub fn version_retention_update(&mut self, retention: VersionRetention) -> Result<(), StorageError> {
+        if MAX_VERSIONS_LIMIT < retention.max_versions {
+            return Err(StorageError::InvalidArgument(format!(
+                "max versions must less than {MAX_VERSIONS_LIMIT}"
+            )));
+        }
+        self.version_retention = retention;
+        self.versions_cursor = None; // 从头开始按新的策略清理
+        Ok(())
+    }
+    // 按保留策略清理历史版本, 每次检查有限的文件, 返回清除的版本数量
+    pub fn versions_expire(&mut self, now: TimestampNanos) -> u64 {
+        use std::ops::Bound;
+        let start = self.versions_cursor.take().map_or(Bound::Unbounded, Bound::Excluded);
+        let paths = self
+            .versions
+            .keys_range((start, Bound::Unbounded))
+            .take(MAX_BATCH_FILES as usize)
+            .collect::<Vec<_>>();
+        if paths.len() == MAX_BATCH_FILES as usize {
+            self.versions_cursor = paths.last().cloned(); // 下次继续
+        }
+        let mut expired = 0;
+        for path in paths {
+            let mut versions = match self.versions.get(&path) {
+                Some(versions) => versions,
+                None => continue,
+            };
+            let removed = versions.retain(&self.version_retention, now);
+            if removed.is_empty() {
+                continue;
+            }
+            expired += removed.len() as u64;
+            self.versions.insert(path.clone(), versions);
+            self.release_versions(&path, removed);
+        }
+        expired
+    }
+
+    // ========== 过期文件 ==========
+
+    // 清除过期的文件, 每次检查有限的文件, 不进入回收站, 返回清除的路径
+    pub fn files_expire(&mut self, now: TimestampNanos) -> Vec<String> {
+        use std::ops::Bound;
+        let start = self.expiring_cursor.take().map_or(Bound::Unbounded, Bound::Excluded);
+        let files = self
+            .files
+            .range((start, Bound::Unbounded))
+            .take(MAX_BATCH_FILES as usize)
+            .map(|entry| entry.value())
+            .collect::<Vec<_>>();
+        if files.len() == MAX_BATCH_FILES as usize {
+            self.expiring_cursor = files.last().map(|file| file.path.clone()); // 下次继续
+        }
+        let expired = files
+            .into_iter()
+            .filter(|file| file.expired(now))
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+        for path in expired.iter() {
+            self.clean_file(path); // 没有其他引用时, 稳定内存中的数据块也会被清除
+        }
+        expired
+    }
+
+    // ========== 回收站 ==========
+
+    // 删除的文件放入回收站, 数据和历史版本继续保留, 直到从回收站清除
+    pub fn trash_file(&mut self, path: &String, now: TimestampNanos, deleter: Option<UserId>) {
+        let file = match self.files.get(path) {
+            Some(file) => file,
+            None => return,
+        };
+        self.next_trash += 1;
+        let id = self.next_trash;
+        self.put_hash(file.hash, trash_ref(id)); // 先引用, 数据不会被释放
+        self.usage_add(file.uploader, file.size); // 回收站中的文件继续计入上传者的用量
+        let versions = self.versions.remove(path).unwrap_or_default(); // 历史版本的用量不变
+        for version in &versions.list {
+            self.put_hash(version.hash, trash_version_ref(id, version.version));
+            self.release_hash(&version.hash, version.size, &version_ref(path, version.version));
+        }
+        self.trash.insert(
+            id,
+            TrashedFile {
+                id,
+                file,
+                deleted: now,
+                deleter,
+                versions,
+            },
+        );
+        self.clean_file(path);
+    }
+    pub fn trash_list(&self, page: &QueryPage, caller: &UserId) -> Result<PageData<QueryTrashedFile>, StorageError> {
+        page.check(MAX_FILES_PAGE_SIZE)
+            .map_err(|e| StorageError::InvalidArgument(e.to_string()))?;
+        let trashed = self
+            .trash
+            .values()
+            .rev() // 最近删除的在前
+            .filter(|trashed| self.readable(&trashed.file.path, caller))
+            .collect::<Vec<_>>();
+        let total = trashed.len() as u64;
+        let data = trashed
+            .into_iter()
+            .skip(((page.page - 1) * page.size as u64) as usize)
+            .take(page.size as usize)
+            .map(QueryTrashedFile::from)
+            .collect();
+        Ok(page.from_data(total, data))
+    }
+    // 回收站中的文件原来的路径
+    pub fn trash_path(&self, id: u64) -> Option<String> {
+        self.trash.get(&id).map(|trashed| trashed.file.path)
+    }
+    // 恢复到原来的路径或者指定的路径, 不覆盖已有的文件
+    // 恢复的文件记在恢复者名下
+    pub fn trash_restore(&mut self, id: u64, to: Option<String>, caller: UserId) -> Result<String, StorageError> {
+        let trashed = self
+            .trash
+            .get(&id)
+            .ok_or_else(|| StorageError::NotFound(trash_ref(id)))?;
+        let path = to.unwrap_or_else(|| trashed.file.path.clone());
+        Self::check_path_and_headers(&path, &trashed.file.headers)?;
+        if self.files.contains_key(&path) {
+            return Err(StorageError::AlreadyExists(path));
+        }
+        if trashed.file.uploader != Some(caller) {
+            self.check_quota(&caller, &[(&path, trashed.file.size)])?; // 恢复自己的文件, 用量不变
+        }
+
+        self.trash.remove(&id);
+        self.usage_sub(trashed.file.uploader, trashed.file.size);
+        let mut file = trashed.file;
+        file.path = path.clone();
+        file.uploader = Some(caller);
+        if file.status != AssetStatus::Mismatched {
+            file.status = self.hash_status(&file.hash);
+        }
+        self.put_hash(file.hash, path.clone());
+        self.release_hash(&file.hash, file.size, &trash_ref(id));
+        self.insert_file(path.clone(), file);
+        let versions = self.append_versions(&path, trashed.versions.clone()); // 历史版本跟随文件恢复
+        self.versions.insert(path.clone(), versions);
+        for version in trashed.versions.list {
+            self.release_hash(&version.hash, version.size, &trash_version_ref(id, version.version));
+        }
+        Ok(path)
+    }
+    // 指定序号时直接清除, 否则清空整个回收站, 文件较多时分批清除
+    pub fn trash_empty(&mut self, ids: Option<Vec<u64>>) -> Result<(), StorageError> {
+        match ids {
+            Some(ids) => {
+                if (MAX_BATCH_FILES as usize) < ids.len() {
+                    return Err(StorageError::InvalidArgument(format!(
+                        "too many files, must less than {MAX_BATCH_FILES}"
+                    )));
+                }
+                for id in ids {
+                    self.purge_trash(id);
+                }
+            }
+            None => self.trash_emptying = Some(self.next_trash),
+        }
+        Ok(())
+    }
+    pub fn trash_emptying(&self) -> bool {
+        self.trash_emptying.is_some()
+    }
+    // 清除正在清空的和超过保留时间的文件, 返回清除的数量
+    pub fn trash_purge(&mut self, now: TimestampNanos) -> u64 {
+        let emptying = self.trash_emptying.unwrap_or_default();
+        let ids = self
+            .trash
+            .iter()
+            .take_while(|entry| *entry.key() <= emptying || entry.value().expired(now, self.trash_retention))
+            .take(MAX_DELETE_FILES as usize)
+            .map(|entry| *entry.key())
+            .collect::<Vec<_>>();
+        if ids.len() < MAX_DELETE_FILES as usize {
+            self.trash_emptying = None; // 已经清空了
+        }
+        for id in ids.iter() {
+            self.purge_trash(*id);
+        }
+        ids.len() as u64
+    }
+    fn purge_trash(&mut self, id: u64) {
+        if let Some(trashed) = self.trash.remove(&id) {
+            self.usage_sub(trashed.file.uploader, trashed.file.size);
+            self.release_hash(&trashed.file.hash, trashed.file.size, &trash_ref(id));
+            for version in trashed.versions.list {
+                self.usage_sub(version.uploader, version.size);
+                self.release_hash(&version.hash, version.size, &trash_version_ref(id, version.version));
+            }
+        }
+    }
+
+    // ========== 访问控制 ==========
+
+    // 最长匹配的前缀规则
+    fn path_acl(&self, path: &str) -> Option<&PathAcl> {
+        use std::ops::Bound;
+        self.acl
+            .range::<str, _>((Bound::Unbounded, Bound::Included(path)))
+            .rev()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, acl)| acl)
+    }
+    // 没有规则时文件是公开的, 上传和删除只按全局权限, 设置了规则则由规则决定, 拥有全局权限也不例外
+    // 以 / 结尾的目录, 目录下更具体的规则也要满足
+    pub fn path_permitted(&self, path: &str, caller: &UserId, permission: &str) -> bool {
+        let roles = self.permission_user_roles(caller);
+        let granted = permission == ACTION_BUSINESS_QUERY
+            || self
+                .parse_permission(permission)
+                .is_ok_and(|permission| self.permission_has(caller, &permission));
+        let permitted = |acl: Option<&PathAcl>| match acl.and_then(|acl| acl.rule(permission)) {
+            Some(rule) => rule.allows(caller, roles),
+            None => granted,
+        };
+        permitted(self.path_acl(path))
+            && (!path.ends_with('/')
+                || self
+                    .acl
+                    .range::<str, _>((std::ops::Bound::Excluded(path), std::ops::Bound::Unbounded))
+                    .take_while(|(prefix, _)| prefix.starts_with(path))
+                    .all(|(_, acl)| permitted(Some(acl))))
+    }
+    fn readable(&self, path: &str, caller: &UserId) -> bool {
+        self.path_permitted(path, caller, ACTION_BUSINESS_QUERY)
+    }
+    pub fn acl_list(&self) -> Vec<(String, PathAcl)> {
+        self.acl
+            .iter()
+            .map(|(prefix, acl)| (prefix.clone(), acl.clone()))
+            .collect()
+    }
+    // 不指定规则时移除该前缀的规则
+    pub fn acl_update(&mut self, prefix: String, acl: Option<PathAcl>) -> Result<(), StorageError> {
+        if !prefix.starts_with('/') {
+            return Err(StorageError::InvalidPath("path must start with /".into()));
+        }
+        match acl {
+            Some(acl) => {
+                if !self.acl.contains_key(&prefix) && MAX_ACL_RULES <= self.acl.len() {
+                    return Err(StorageError::InvalidArgument(format!(
+                        "too many rules, must less than {MAX_ACL_RULES}"
+                    )));
+                }
+                self.acl.insert(prefix, acl);
+            }
+            None => {
+                self.acl.remove(&prefix);
+            }
+        }
+        Ok(())
+    }
+
+    // ========== 存储配额 ==========
+
+    fn usage_add(&mut self, uploader: Option<UserId>, size: u64) {
+        if let Some(uploader) = uploader {
+            self.usage.entry(uploader).or_default().add(size);
+        }
+    }
+    fn usage_sub(&mut self, uploader: Option<UserId>, size: u64) {
+        if let Some(uploader) = uploader
+            && let Some(usage) = self.usage.get_mut(&uploader)
+        {
+            usage.sub(size);
+            if usage.is_empty() {
+                self.usage.remove(&uploader);
+            }
+        }
+    }
+    // 旧版本升级后, 从文件索引重建用量
+    pub fn usage_reindex(&mut self) {
+        if self.usage_indexed {
+            return;
+        }
+        self.usage.clear();
+        let files = self.files.values().map(|file| (file.uploader, file.size));
+        let versions = self
+            .versions
+            .values()
+            .flat_map(|versions| versions.list)
+            .map(|version| (version.uploader, version.size));
+        let trashed = self.trash.values().flat_map(|trashed| {
+            let versions = trashed
+                .versions
+                .list
+                .iter()
+                .map(|version| (version.uploader, version.size));
+            std::iter::once((trashed.file.uploader, trashed.file.size)).chain(versions.collect::<Vec<_>>())
+        });
+        for (uploader, size) in files.chain(versions).chain(trashed).collect::<Vec<_>>() {
+            self.usage_add(uploader, size);
+        }
+        self.usage_indexed = true;
+    }
+    fn user_quota(&self, user: &UserId) -> Option<StorageQuota> {
+        self.quotas.quota(user, self.permission_user_roles(user))
+    }
+    // 还没有上传完成的文件, 指定的路径除外
+    fn uploading_usage(&self, user: &UserId, except: &[(&str, u64)]) -> StorageUsage {
+        let mut usage = StorageUsage::default();
+        for file in self.uploading.values() {
+            if file.uploader.as_ref() == Some(user) && except.iter().all(|(path, _)| *path != file.path) {
+                usage.add(file.size);
+            }
+        }
+        usage
+    }
+    // 新增文件后是否超出配额, 上传中的文件也计入, 覆盖自己的文件并且不保留历史版本时扣除原来的大小
+    fn check_quota(&self, user: &UserId, files: &[(&str, u64)]) -> Result<(), StorageError> {
+        let quota = match self.user_quota(user) {
+            Some(quota) => quota,
+            None => return Ok(()),
+        };
+        let mut usage = self.usage.get(user).copied().unwrap_or_default();
+        let uploading = self.uploading_usage(user, files);
+        usage.bytes = usage.bytes.saturating_add(uploading.bytes);
+        usage.files = usage.files.saturating_add(uploading.files);
+        for (path, size) in files {
+            if let Some(exist) = self.files.get(&path.to_string())
+                && exist.uploader.as_ref() == Some(user)
+                && self.version_retention.max_versions == 0
+            {
+                usage.sub(exist.size);
+            }
+            usage.add(*size);
+        }
+        quota.check(&usage)
+    }
+    pub fn usage_query(&self, user: UserId) -> QueryUsage {
+        QueryUsage {
+            user,
+            usage: self.usage.get(&user).copied().unwrap_or_default(),
+            uploading: self.uploading_usage(&user, &[]),
+            quota: self.user_quota(&user),
+        }
+    }
+    pub fn quota_update(&mut self, target: QuotaTarget, quota: Option<StorageQuota>) -> Result<(), StorageError> {
+        if let QuotaTarget::Role(role) = &target
+            && role.is_empty()
+        {
+            return Err(StorageError::InvalidArgument("role can not be empty".into()));
+        }
+        self.quotas.update(target, quota);
+        Ok(())
+    }
+
+    // ========== 删除目录 ==========
+
+    pub fn delete_dir(&mut self, path: String, recursive: bool, deleter: Option<UserId>) -> Result<(), StorageError> {
+        let dir = DeletingDir::new(Self::dir_prefix(&path)?, recursive, deleter);
+        // 1. 正在上传的文件直接清除
+        let uploading = self
+            .uploading
+            .keys()
+            .filter(|path| dir.contains(path))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in uploading {
+            self.clean_uploading(&path);
+        }
+        // 2. 已经保存的文件分批删除
+        self.deleting.push_back(dir);
+        Ok(())
+    }
+    pub fn deleting(&self) -> bool {
+        !self.deleting.is_empty()
+    }
+    pub fn delete_dirs(&mut self, now: TimestampNanos) -> Vec<String> {
+        use std::ops::Bound;
+        let mut budget = MAX_DELETE_FILES as usize; // 本次消息的扫描额度
+        let mut deleted = Vec::new();
+        while 0 < budget {
+            let dir = match self.deleting.front() {
+                Some(dir) => dir.clone(),
+                None => break,
+            };
+            let start = dir
+                .cursor
+                .clone()
+                .map_or_else(|| Bound::Included(dir.prefix.clone()), Bound::Excluded);
+            let paths = self
+                .files
+                .keys_range((start, Bound::Unbounded))
+                .take_while(|path| path.starts_with(&dir.prefix))
+                .take(budget)
+                .collect::<Vec<_>>();
+            let done = paths.len() < budget;
+            budget -= paths.len();
+            for path in &paths {
+                if dir.contains(path) {
+                    self.trash_file(path, now, dir.deleter);
+                    deleted.push(path.clone());
+                }
+            }
+            if done {
+                self.deleting.pop_front();
+            } else if let Some(dir) = self.deleting.front_mut() {
+                dir.cursor = paths.last().cloned(); // 子目录的文件不删除时需要跳过
+            }
+        }
+        deleted
+    }
+
+    // ========== 后台计算 hash ==========
+
+    pub fn digesting(&self) -> bool {
+        !self.digesting.is_empty()
+    }
+    // 继续计算提交的上传数据的 hash, 返回计算完成的路径及校验结果
+    pub fn digest(&mut self) -> Vec<(String, Result<(), StorageError>)> {
+        let mut budget = MAX_DIGEST_SIZE; // 本次消息的计算额度
+        let mut digested = Vec::new();
+        while 0 < budget {
+            let Some(path) = self.digesting.keys().next().cloned() else {
+                break;
+            };
+            match self.digest_step(&path, &mut budget) {
+                Some(result) => digested.push((path, result)),
+                None => break,
+            }
+        }
+        digested
+    }
+
+    // ========== 后台校验 ==========
+
+    pub fn verifying(&self) -> bool {
+        self.verifier.is_some() || self.verifying.iter().any(|hash| self.assets.contains_key(hash))
+    }
+    // 重新计算直接采用上传 hash 保存的数据, 返回校验失败的数据及引用的文件
+    pub fn verify(&mut self) -> Vec<(HashDigest, Vec<String>)> {
+        let mut budget = MAX_VERIFY_BUCKETS; // 本次消息的校验额度
+        let mut mismatched = Vec::new();
+        while 0 < budget {
+            let mut verifier = match self.verifier.take() {
+                Some(verifier) => verifier,
+                None => {
+                    // 还在写入的数据稍后再校验
+                    let next = self
+                        .verifying
+                        .iter()
+                        .find(|hash| self.assets.contains_key(*hash))
+                        .copied();
+                    let Some(hash) = next else { break };
+                    let Some(size) = self.hash_size(&hash) else {
+                        self.verifying.remove(&hash);
+                        continue;
+                    };
+                    AssetVerifier::new(hash, size)
+                }
+            };
+            if !verifier.step(&mut budget) {
+                self.verifier = Some(verifier);
+                break;
+            }
+            let hash = verifier.hash;
+            self.verifying.remove(&hash);
+            if verifier.matched() {
+                continue;
+            }
+            // 数据与 hash 不一致, 删除数据及引用的文件, 需要重新上传
+            let paths = self.unlink_assets(&hash);
+            mismatched.push((hash, paths));
+        }
+        mismatched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_assemble_staged_chunks() {
+        let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+        let file = UploadingFile {
+            session: 1,
+            path: "/staged".to_string(),
+            headers: vec![],
+            hash: HashDigest::default(),
+            size: data.len() as u64,
+            chunk_size: 7,
+            chunks: 143,
+            chunked: vec![true; 143],
+            created: 0.into(),
+            touched: 0.into(),
+            expires_at: None,
+            uploader: None,
+            failed: None,
+        };
+        for (index, chunk) in data.chunks(7).enumerate().rev() {
+            file.write(index as u32, chunk.to_vec());
+        }
+        assert_eq!(file.read(0, 1000), data);
+        assert_eq!(file.read(5, 23), data[5..23]);
+
+        use sha2::Digest;
+        let hash = HashDigest(sha2::Sha256::digest(&data).into());
+        let mut digester = UploadDigester::default();
+        let mut budget = 500;
+        assert!(!digester.step(&file, &mut budget)); // 额度用完, 后续的消息继续计算
+        assert_eq!(budget, 0);
+        let mut budget = MAX_DIGEST_SIZE;
+        assert!(digester.step(&file, &mut budget));
+        assert_eq!(digester.finish(), (hash, 1000));
+
+        let mut commit = AssetCommit::new(file);
+        let mut budget = MAX_COMMIT_BUCKETS;
+        assert!(commit.step(&hash, &mut budget));
+        assert_eq!(budget, MAX_COMMIT_BUCKETS - 1);
+        assert_eq!(AssetData {}.slice(&hash, 1000, 0, 1000).to_vec(), data);
+        assert!(init_uploading_data().is_empty()); // 暂存的数据块已经删除
+    }
+
+    #[test]
+    fn should_check_chunk_size() {
+        assert_eq!(InnerState::check_size(100, 100), Ok(()));
+        assert_eq!(InnerState::check_size(100, 1024), Ok(())); // 只有一块
+        assert_eq!(InnerState::check_size(MAX_FILE_SIZE, MIN_CHUNK_SIZE), Ok(()));
+        assert_eq!(
+            InnerState::check_size(100, 0),
+            Err(StorageError::InvalidArgument("chunk size can not be 0".into()))
+        );
+        assert_eq!(
+            InnerState::check_size(MAX_FILE_SIZE, 1),
+            Err(StorageError::InvalidArgument(format!(
+                "chunk size must be at least {MIN_CHUNK_SIZE} unless the file has only one chunk"
+            )))
+        );
+        assert!(InnerState::check_size(MIN_CHUNK_SIZE as u64 + 1, MIN_CHUNK_SIZE - 1).is_err());
+    }
+
+    #[test]
+    fn should_track_upload_session() {
+        let mut state = InnerState::default();
+        let (alice, bob) = (UserId::from_slice(&[1]), UserId::from_slice(&[2]));
+        let data = b"hello world".to_vec();
+        state.uploading.insert(
+            "/session.txt".to_string(),
+            UploadingFile {
+                session: 1,
+                path: "/session.txt".to_string(),
+                headers: vec![],
+                hash: HashDigest::default(), // 错误的 hash
+                size: data.len() as u64,
+                chunk_size: 4,
+                chunks: 3,
+                chunked: vec![false; 3],
+                created: 0.into(),
+                touched: 10.into(),
+                expires_at: None,
+                uploader: Some(alice),
+                failed: None,
+            },
+        );
+        if let Some(file) = state.uploading.get_mut("/session.txt") {
+            assert_eq!(file.put(2, data[8..].to_vec()), Ok(()));
+            assert_eq!(
+                file.put(3, data[8..].to_vec()),
+                Err(StorageError::ChunkIndexOutOfRange { index: 3, chunks: 3 })
+            );
+            assert_eq!(
+                file.put(0, data[..3].to_vec()),
+                Err(StorageError::ChunkLengthMismatch {
+                    index: 0,
+                    expected: 4,
+                    actual: 3
+                })
+            );
+        }
+        assert_eq!(
+            state.upload_commit(1, &alice),
+            Err(StorageError::UploadNotFinished { uploaded: 1, chunks: 3 })
+        );
+
+        let status = state.upload_status(1);
+        assert!(status.is_some_and(|status| status.chunked == vec![false, false, true] && status.uploaded == 3));
+
+        if let Some(file) = state.uploading.get_mut("/session.txt") {
+            assert_eq!(file.put(0, data[..4].to_vec()), Ok(()));
+            assert_eq!(file.put(1, data[4..8].to_vec()), Ok(()));
+        }
+        // 只有上传者自己可以操作上传会话
+        assert_eq!(state.upload_commit(1, &bob), Err(StorageError::SessionNotFound(1)));
+        assert_eq!(state.upload_abort(1, &bob), Err(StorageError::SessionNotFound(1)));
+        let chunk = UploadChunkArg {
+            session: 1,
+            index: 0,
+            chunk: data[..4].to_vec(),
+        };
+        assert_eq!(state.upload_put(chunk, &bob), Err(StorageError::SessionNotFound(1)));
+
+        let commit = state.upload_commit(1, &alice);
+        assert!(matches!(commit, Err(StorageError::HashMismatch { .. }))); // hash 校验失败
+        assert_eq!(state.upload_commit(2, &alice), Err(StorageError::SessionNotFound(2)));
+        assert!(state.upload_status(1).is_some());
+
+        // 没有计算完的数据在后台继续计算, 期间不能修改
+        state.digesting.insert("/session.txt".to_string(), true);
+        assert_eq!(state.digest_step(&"/session.txt".to_string(), &mut 4), None);
+        assert_eq!(
+            state.upload_commit(1, &alice),
+            Err(StorageError::Committing("/session.txt".into()))
+        );
+        assert_eq!(
+            state.upload_put(
+                UploadChunkArg {
+                    session: 1,
+                    index: 0,
+                    chunk: data[..4].to_vec(),
+                },
+                &alice
+            ),
+            Err(StorageError::Committing("/session.txt".into()))
+        );
+        assert!(state.upload_status(1).is_some_and(|status| status.committing));
+        state.uploading_timeout = 5.into();
+        assert!(state.upload_expire(16.into()).is_empty());
+        let digested = state.digest();
+        assert!(matches!(&digested[..], [(path, Err(StorageError::HashMismatch { .. }))] if path == "/session.txt"));
+        assert!(!state.digesting());
+        // 后台校验失败的结果可以从上传进度中看到
+        let status = state.upload_status(1);
+        assert!(status.is_some_and(|status| {
+            !status.committing && matches!(status.failed, Some(StorageError::HashMismatch { .. }))
+        }));
+
+        // 过期的上传会被清除
+        assert!(state.upload_expire(15.into()).is_empty());
+        assert_eq!(state.upload_expire(16.into()), vec![(1, "/session.txt".to_string())]);
+        assert!(state.upload_status(1).is_none());
+        assert!(init_uploading_data().is_empty()); // 暂存的数据块已经删除
+    }
+
+    fn put_test_asset(state: &mut InnerState, path: &str, hash: HashDigest, data: &[u8]) {
+        AssetData::write(
+            &hash,
+            data.len() as u64,
+            0,
+            AssetData::buckets(data.len() as u64),
+            |offset, offset_end| data[offset..offset_end].to_vec(),
+        );
+        state.assets.insert(hash, AssetData {});
+        state.insert_file(
+            path.to_string(),
+            AssetFile {
+                path: path.to_string(),
+                created: 0.into(),
+                modified: 0.into(),
+                headers: vec![],
+                hash,
+                size: data.len() as u64,
+                status: AssetStatus::Committed,
+                uploader: None,
+                expires_at: None,
+            },
+        );
+        state.put_hash(hash, path.to_string());
+    }
+
+    #[test]
+    fn should_flag_mismatched_assets() {
+        use sha2::Digest;
+        let mut state = InnerState::default();
+        let data = b"trusted data".to_vec();
+        let good = HashDigest(sha2::Sha256::digest(&data).into());
+        let bad = HashDigest([7; 32]); // 上传时声明的错误 hash
+        put_test_asset(&mut state, "/good.txt", good, &data);
+        put_test_asset(&mut state, "/bad.txt", bad, &data);
+        state.verifying.insert(good);
+        state.verifying.insert(bad);
+
+        state.trash_file(&"/bad.txt".to_string(), 1.into(), None);
+        put_test_asset(&mut state, "/bad.txt", bad, &data);
+
+        assert!(state.verifying());
+        assert_eq!(state.verify(), vec![(bad, vec!["/bad.txt".to_string()])]);
+        assert!(!state.verifying());
+        // 校验失败的数据和引用的文件, 回收站中的文件一并删除
+        assert!(state.files.get(&"/bad.txt".to_string()).is_none());
+        assert!(state.trash.is_empty());
+        assert!(state.hashes.get(&bad).is_none());
+        assert!(!state.assets.contains_key(&bad));
+        let file = state.files.get(&"/good.txt".to_string());
+        assert!(file.is_some_and(|file| file.status == AssetStatus::Committed));
+    }
+
+    #[test]
+    fn should_not_republish_unverified_paths() {
+        use sha2::Digest;
+        let mut state = InnerState::default();
+        let data = b"genuine data".to_vec();
+        let hash = HashDigest(sha2::Sha256::digest(&data).into());
+        put_test_asset(&mut state, "/evil.txt", hash, b"forged data!"); // 声明了别人的 hash, 没有校验
+        state.verifying.insert(hash);
+
+        // 校验过的上传替换了未校验的数据
+        state.drop_assets(&hash);
+        let file = UploadingFile {
+            session: 1,
+            path: "/mine.txt".to_string(),
+            headers: vec![],
+            hash,
+            size: data.len() as u64,
+            chunk_size: 1024,
+            chunks: 1,
+            chunked: vec![true],
+            created: 0.into(),
+            touched: 0.into(),
+            expires_at: None,
+            uploader: None,
+            failed: None,
+        };
+        file.write(0, data.clone());
+        state.committing.insert(hash, AssetCommit::new(file));
+        state.put_file_at("/mine.txt".to_string(), vec![], hash, data.len() as u64, 1.into(), None);
+        assert_eq!(state.commit(), vec!["/mine.txt".to_string()]);
+
+        // 原来引用的文件需要重新上传或者证明持有数据, 不能访问
+        let file = state.files.get(&"/evil.txt".to_string());
+        assert!(file.is_some_and(|file| file.status == AssetStatus::Mismatched));
+        assert_eq!(
+            state.download("/evil.txt".to_string(), 1.into()),
+            Err(StorageError::NotFound("/evil.txt".to_string()))
+        );
+        assert_eq!(
+            state.download_by("/evil.txt".to_string(), 0, 1, None, 1.into()),
+            Err(StorageError::NotFound("/evil.txt".to_string()))
+        );
+        assert_eq!(state.download("/mine.txt".to_string(), 1.into()), Ok(data.clone()));
+
+        // 重新上传相同的数据后可以访问
+        state.put_file_at("/evil.txt".to_string(), vec![], hash, data.len() as u64, 2.into(), None);
+        assert_eq!(state.download("/evil.txt".to_string(), 2.into()), Ok(data));
+    }
+
+    #[test]
+    fn should_check_possession_proof() {
+        use sha2::Digest;
+        let mut state = InnerState::default();
+        let data = (0..=255u8).cycle().take(3000).collect::<Vec<_>>();
+        let hash = HashDigest(sha2::Sha256::digest(&data).into());
+        put_test_asset(&mut state, "/origin.bin", hash, &data);
+
+        let nonce = HashDigest([1; 32]);
+        state.challenges.insert(
+            nonce,
+            UploadChallengeData {
+                hash,
+                offset: 100,
+                size: 200,
+                created: 10.into(),
+            },
+        );
+        let prove = state.upload_prove(
+            UploadProofArg {
+                nonce,
+                path: "/copy.bin".to_string(),
+                headers: vec![],
+                proof: UploadChallenge::answer(&nonce, &data[101..301]), // 错误的数据
+            },
+            10.into(),
+            UserId::anonymous(),
+        );
+        assert_eq!(prove, Err(StorageError::ProofMismatch));
+        assert!(state.files.get(&"/copy.bin".to_string()).is_none());
+
+        // 过期的挑战会被清除
+        let nonce = HashDigest([2; 32]);
+        state.challenges.insert(
+            nonce,
+            UploadChallengeData {
+                hash,
+                offset: 0,
+                size: 200,
+                created: 10.into(),
+            },
+        );
+        state.uploading_timeout = 5.into();
+        state.upload_expire(15.into());
+        assert!(state.challenges.contains_key(&nonce));
+        state.upload_expire(16.into());
+        assert!(state.challenges.is_empty());
+    }
+
+    #[test]
+    fn should_list_files_by_page() {
+        let mut state = InnerState::default();
+        for path in ["/a/1", "/a/3", "/b/1", "/a/2", "/0"] {
+            put_test_asset(&mut state, path, HashDigest::default(), b"data");
+        }
+        let paths = |page: &QueryFilePage| page.page.data.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        let page = QueryPage { page: 1, size: 2 };
+
+        let first = state.files_by_page(&page, "/a/".into(), None, FileSort::PathAsc, &UserId::anonymous());
+        let first = first.unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            (first.page.total, paths(&first)),
+            (3, vec!["/a/1".to_string(), "/a/2".to_string()])
+        );
+        assert_eq!(first.next, Some("/a/2".to_string()));
+
+        let second = state.files_by_page(&page, "/a/".into(), first.next, FileSort::PathAsc, &UserId::anonymous());
+        let second = second.unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!((paths(&second), second.next), (vec!["/a/3".to_string()], None));
+
+        let second = state.files_by_page(
+            &QueryPage { page: 2, size: 2 },
+            "/a/".into(),
+            None,
+            FileSort::PathAsc,
+            &UserId::anonymous(),
+        );
+        assert!(second.is_ok_and(|second| paths(&second) == vec!["/a/3".to_string()]));
+
+        let desc = state.files_by_page(&page, "/a/".into(), None, FileSort::PathDesc, &UserId::anonymous());
+        let desc = desc.unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(paths(&desc), vec!["/a/3".to_string(), "/a/2".to_string()]);
+        let desc = state.files_by_page(&page, "/a/".into(), desc.next, FileSort::PathDesc, &UserId::anonymous());
+        assert!(desc.is_ok_and(|desc| paths(&desc) == vec!["/a/1".to_string()] && desc.next.is_none()));
+
+        // 前缀之后还有文件时, 倒序从前缀的上界开始, 翻页时不统计总数
+        for path in ["/a0", "/ab", "/a/\u{10FFFF}"] {
+            put_test_asset(&mut state, path, HashDigest::default(), b"data");
+        }
+        assert_eq!(
+            InnerState::prefix_upper("/a/"),
+            std::ops::Bound::Excluded("/a0".to_string())
+        );
+        assert_eq!(InnerState::prefix_upper("\u{10FFFF}"), std::ops::Bound::Unbounded);
+        let desc = state.files_by_page(&page, "/a/".into(), None, FileSort::PathDesc, &UserId::anonymous());
+        let desc = desc.unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(desc.page.total, 4);
+        assert_eq!(paths(&desc), vec!["/a/\u{10FFFF}".to_string(), "/a/3".to_string()]);
+        let desc = state.files_by_page(&page, "/a/".into(), desc.next, FileSort::PathDesc, &UserId::anonymous());
+        let desc = desc.unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(desc.page.total, 0);
+        assert_eq!(paths(&desc), vec!["/a/2".to_string(), "/a/1".to_string()]);
+        let desc = state.files_by_page(
+            &page,
+            "/a/".into(),
+            Some("/b".into()),
+            FileSort::PathDesc,
+            &UserId::anonymous(),
+        );
+        assert!(desc.is_ok_and(|desc| paths(&desc) == vec!["/a/\u{10FFFF}".to_string(), "/a/3".to_string()]));
+
+        let all = state.files_by_page(
+            &QueryPage { page: 1, size: 10 },
+            String::new(),
+            None,
+            FileSort::PathAsc,
+            &UserId::anonymous(),
+        );
+        assert!(all.is_ok_and(|all| all.page.total == 8 && all.page.data[0].path == "/0"));
+
+        let wrong = state.files_by_page(
+            &QueryPage { page: 0, size: 2 },
+            String::new(),
+            None,
+            FileSort::PathAsc,
+            &UserId::anonymous(),
+        );
+        assert!(matches!(wrong, Err(StorageError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn should_list_and_delete_dir() {
+        let mut state = InnerState::default();
+        for path in ["/a/1", "/a/b/1", "/a/b/c/1", "/a/b.txt", "/a/d/1", "/ab"] {
+            put_test_asset(&mut state, path, HashDigest::default(), b"data");
+        }
+
+        let anonymous = UserId::anonymous();
+        let dir = state
+            .list_dir("/a".into(), None, 10, &anonymous)
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(dir.path, "/a/");
+        let dirs = dir.dirs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(dirs, vec!["b", "d"]);
+        let files = dir.files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(files, vec!["/a/1", "/a/b.txt"]);
+        assert_eq!(dir.next, None);
+        assert!(matches!(
+            state.list_dir("a".into(), None, 10, &anonymous),
+            Err(StorageError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            state.list_dir("/a".into(), None, 0, &anonymous),
+            Err(StorageError::InvalidArgument(_))
+        ));
+
+        // 按游标分页, 子目录的游标以 / 结尾
+        let mut pages = vec![];
+        let mut cursor = None;
+        loop {
+            let dir = state
+                .list_dir("/a/".into(), cursor, 1, &anonymous)
+                .unwrap_or_else(|e| panic!("{e}"));
+            pages.extend(dir.dirs.iter().map(|d| d.name.clone()));
+            pages.extend(dir.files.iter().map(|f| f.path.clone()));
+            cursor = dir.next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages, vec!["/a/1", "/a/b.txt", "b", "d"]);
+        let dir = state
+            .list_dir("/a/".into(), Some("/a/b/".into()), 10, &anonymous)
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(dir.dirs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["d"]);
+        assert!(dir.files.is_empty());
+
+        // 不递归时只删除直接文件
+        assert!(state.delete_dir("/a/b/".into(), false, None).is_ok());
+        assert!(state.deleting());
+        assert_eq!(state.delete_dirs(0.into()), vec!["/a/b/1".to_string()]);
+        assert!(!state.deleting());
+        assert!(state.files.contains_key(&"/a/b/c/1".to_string()));
+
+        // 递归删除整个子树, 相同前缀的文件不受影响
+        assert!(state.delete_dir("/a".into(), true, None).is_ok());
+        assert_eq!(state.delete_dirs(0.into()).len(), 4);
+        assert_eq!(state.files.keys().collect::<Vec<_>>(), vec!["/ab".to_string()]);
+        assert!(state.assets.contains_key(&HashDigest::default()));
+    }
+
+    #[test]
+    fn should_purge_orphans_in_batches() {
+        let mut state = InnerState::default();
+        put_test_asset(&mut state, "/a", HashDigest::from([1; 32]), b"a");
+        for hash in [[0; 32], [2; 32], [3; 32]] {
+            AssetData::write(&HashDigest::from(hash), 1, 0, 1, |_, _| vec![0]); // 没有被引用的数据块
+        }
+
+        // 每批只扫描有限的数据块, 按游标继续
+        let (purged, cursor) = AssetData::purge(None, 2, |hash| state.assets.contains_key(hash));
+        assert_eq!(purged, 1);
+        assert!(cursor.is_some());
+        let (purged, cursor) = AssetData::purge(cursor.as_deref(), 2, |hash| state.assets.contains_key(hash));
+        assert_eq!(purged, 2);
+        let (purged, cursor) = AssetData::purge(cursor.as_deref(), 2, |hash| state.assets.contains_key(hash));
+        assert_eq!((purged, cursor), (0, None)); // 扫描完了
+        assert!(AssetData::bucket(&HashDigest::from([1; 32]), 0).is_some());
+
+        AssetData::write(&HashDigest::from([4; 32]), 1, 0, 1, |_, _| vec![0]);
+        assert_eq!(state.purge_orphans(), 1);
+        assert!(!state.purging());
+        assert_eq!(state.purge_step(), 0);
+        assert!(AssetData::bucket(&HashDigest::from([4; 32]), 0).is_none());
+    }
+
+    #[test]
+    fn should_move_and_copy_files() {
+        let mut state = InnerState::default();
+        let (one, two) = (HashDigest::from([1; 32]), HashDigest::from([2; 32]));
+        put_test_asset(&mut state, "/a/1", one, b"one");
+        put_test_asset(&mut state, "/a/b/2", two, b"two");
+        put_test_asset(&mut state, "/c", two, b"two");
+        state.version_retention.max_versions = 0; // 只检查数据的引用, 覆盖时的历史版本见 should_keep_file_versions
+        let paths = |state: &InnerState| state.files.keys().collect::<Vec<_>>();
+
+        // 复制不覆盖, 目标可以指向相同的数据
+        assert!(matches!(
+            state.copy_files("/a/1".into(), "/c".into(), None),
+            Err(StorageError::AlreadyExists(_))
+        ));
+        assert!(state.copy_files("/a/".into(), "/d".into(), None).is_ok());
+        assert_eq!(state.download("/d/b/2".into(), 0.into()), Ok(b"two".to_vec()));
+        assert_eq!(state.hashes.get(&two).map(|HashedPath(set)| set.len()), Some(3));
+
+        // 移动目录, 保留创建时间
+        assert!(matches!(
+            state.move_files("/a/".into(), "/a/b/".into(), false, 0.into()),
+            Err(StorageError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            state.move_files("/a/".into(), "/d/".into(), false, 0.into()),
+            Err(StorageError::AlreadyExists(_))
+        ));
+        assert!(state.move_files("/a/".into(), "/e/".into(), false, 0.into()).is_ok());
+        assert_eq!(
+            paths(&state),
+            ["/c", "/d/1", "/d/b/2", "/e/1", "/e/b/2"].map(String::from)
+        );
+        assert_eq!(state.files.get(&"/e/1".to_string()).map(|f| f.created), Some(0.into()));
+
+        // 覆盖时释放原来的数据, 其他文件还在引用的数据不会删除
+        assert!(state.move_files("/e/1".into(), "/c".into(), true, 0.into()).is_ok());
+        assert!(state.move_files("/d/1".into(), "/d/b/2".into(), true, 0.into()).is_ok());
+        assert_eq!(state.download("/c".into(), 0.into()), Ok(b"one".to_vec()));
+        assert_eq!(state.hashes.get(&two).map(|HashedPath(set)| set.len()), Some(1));
+        assert!(state.move_files("/c".into(), "/e/b/2".into(), true, 0.into()).is_ok());
+        assert_eq!(paths(&state), ["/d/b/2", "/e/b/2"].map(String::from));
+        assert!(!state.assets.contains_key(&two));
+        assert!(matches!(
+            state.download("/c".into(), 0.into()),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn should_update_headers() {
+        let mut state = InnerState::default();
+        for path in ["/a/1", "/a/b/2", "/c"] {
+            put_test_asset(&mut state, path, HashDigest::default(), b"data");
+        }
+        let headers = vec![("Content-Type".to_string(), "text/plain".to_string())];
+        let file = |state: &InnerState, path: &str| state.files.get(&path.to_string()).unwrap_or_else(|| panic!());
+
+        assert_eq!(
+            state.update_headers("/c".into(), headers.clone(), 1.into()),
+            Ok(vec!["/c".to_string()])
+        );
+        assert_eq!(
+            state
+                .update_headers("/a/".into(), headers.clone(), 2.into())
+                .map(|c| c.len()),
+            Ok(2)
+        );
+        let updated = file(&state, "/a/b/2");
+        assert_eq!(
+            (updated.headers, updated.modified, updated.created),
+            (headers, 2.into(), 0.into())
+        );
+        assert_eq!(file(&state, "/c").modified, 1.into());
+        assert_eq!(updated.hash, HashDigest::default());
+        let versions = state.file_versions("/c".into()).unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(versions.iter().map(|v| v.headers.len()).collect::<Vec<_>>(), vec![0]);
+        assert_ne!(file(&state, "/c").etag(), HashDigest::default().hex()); // 缓存标识随 headers 改变
+
+        let large = vec![("x".repeat(65), String::new())];
+        assert!(matches!(
+            state.update_headers("/c".into(), large, 3.into()),
+            Err(StorageError::InvalidHeader(_))
+        ));
+        assert!(matches!(
+            state.update_headers("/d".into(), vec![], 3.into()),
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(matches!(
+            state.update_headers("/d/".into(), vec![], 3.into()),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn should_keep_file_versions() {
+        let mut state = InnerState::default();
+        let (one, two, three) = (
+            HashDigest::from([1; 32]),
+            HashDigest::from([2; 32]),
+            HashDigest::from([3; 32]),
+        );
+        put_test_asset(&mut state, "/a", one, b"one");
+        put_test_asset(&mut state, "/x", three, b"three"); // 只用来保留数据
+        state.put_file_at("/a".into(), vec![], two, 3, 1.into(), None);
+        state.put_file_at("/a".into(), vec![], three, 5, 2.into(), None);
+        assert_eq!(state.hash_size(&one), Some(3)); // 只被历史版本引用的数据
+
+        let versions = state.file_versions("/a".into()).unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(versions.iter().map(|v| v.version).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(
+            state.download_by("/a".into(), 0, 3, Some(1), 0.into()),
+            Ok(b"one".to_vec())
+        );
+        assert!(matches!(
+            state.download_by("/a".into(), 0, 3, Some(3), 0.into()),
+            Err(StorageError::NotFound(_))
+        ));
+
+        // 恢复之后, 当前的内容也成为历史版本
+        assert!(state.restore_version("/a".into(), 1, 3.into(), None).is_ok());
+        assert_eq!(state.download("/a".into(), 0.into()), Ok(b"one".to_vec()));
+        assert_eq!(state.versions.get(&"/a".to_string()).map(|v| v.next), Some(3));
+
+        // 保留策略
+        let retention = VersionRetention {
+            max_versions: 2,
+            max_age: Some(10.into()),
+        };
+        assert!(state.version_retention_update(retention).is_ok());
+        assert_eq!(state.versions_expire(13.into()), 2); // 版本 1 超出数量, 版本 2 超出时间
+        assert_eq!(state.file_versions("/a".into()).map(|v| v.len()), Ok(1));
+        assert!(state.hashes.get(&two).is_none());
+
+        // 移动时历史版本跟随文件, 删除时一并释放
+        assert!(state.move_files("/a".into(), "/b".into(), false, 0.into()).is_ok());
+        assert_eq!(
+            state.download_by("/b".into(), 0, 5, Some(3), 0.into()),
+            Ok(b"three".to_vec())
+        );
+
+        // 移动覆盖时, 被覆盖的文件和普通的覆盖一样保存为历史版本
+        put_test_asset(&mut state, "/c", two, b"two");
+        assert!(state.move_files("/c".into(), "/b".into(), true, 13.into()).is_ok());
+        let versions = state.file_versions("/b".into()).unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(versions.iter().map(|v| v.version).collect::<Vec<_>>(), vec![4, 3]);
+        assert_eq!(state.download("/b".into(), 0.into()), Ok(b"two".to_vec()));
+        assert_eq!(
+            state.download_by("/b".into(), 0, 3, Some(4), 0.into()),
+            Ok(b"one".to_vec())
+        );
+
+        state.clean_file(&"/b".to_string());
+        assert!(state.versions.is_empty());
+        assert!(state.hashes.get(&one).is_none());
+        assert!(state.hashes.get(&two).is_none());
+        assert_eq!(state.hashes.get(&three).map(|HashedPath(set)| set.len()), Some(1));
+    }
+
+    #[test]
+    fn should_keep_trash() {
+        let mut state = InnerState::default();
+        let (one, two) = (HashDigest::from([1; 32]), HashDigest::from([2; 32]));
+        put_test_asset(&mut state, "/a", one, b"one");
+        put_test_asset(&mut state, "/b", two, b"two");
+        state.trash_retention = 10.into();
+
+        // 删除后数据仍然保留
+        state.trash_file(&"/a".to_string(), 1.into(), None);
+        state.trash_file(&"/b".to_string(), 5.into(), None);
+        assert!(!state.files.contains_key(&"/a".to_string()));
+        assert!(state.assets.contains_key(&one));
+        assert_eq!(state.hash_size(&one), Some(3)); // 只被回收站引用的数据
+        let page = state
+            .trash_list(&QueryPage { page: 1, size: 10 }, &UserId::anonymous())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(page.data.iter().map(|t| t.id).collect::<Vec<_>>(), vec![2, 1]);
+
+        // 恢复到指定的路径, 不覆盖已有的文件
+        put_test_asset(&mut state, "/a", two, b"two");
+        assert_eq!(
+            state.trash_restore(1, None, UserId::anonymous()),
+            Err(StorageError::AlreadyExists("/a".into()))
+        );
+        assert_eq!(
+            state.trash_restore(1, Some("/c".into()), UserId::anonymous()),
+            Ok("/c".to_string())
+        );
+        assert_eq!(state.download("/c".into(), 0.into()), Ok(b"one".to_vec()));
+        assert_eq!(state.hashes.get(&one).map(|HashedPath(set)| set.len()), Some(1));
+
+        // 超过保留时间自动清除
+        state.trash_file(&"/c".to_string(), 12.into(), None);
+        assert_eq!(state.trash_purge(12.into()), 0);
+        assert_eq!(state.trash_purge(16.into()), 1);
+        assert!(state.trash.get(&2).is_none());
+        assert!(state.assets.contains_key(&two));
+
+        // 清空回收站
+        assert!(state.trash_empty(None).is_ok());
+        assert!(state.trash_emptying());
+        assert_eq!(state.trash_purge(16.into()), 1);
+        assert!(!state.trash_emptying());
+        assert!(state.trash.is_empty());
+        assert!(!state.assets.contains_key(&one));
+
+        // 历史版本跟随文件进入回收站, 恢复时一并恢复, 清除时一并释放
+        let three = HashDigest::from([3; 32]);
+        put_test_asset(&mut state, "/d", one, b"one");
+        put_test_asset(&mut state, "/x", three, b"three"); // 只用来保留数据
+        state.put_file_at("/d".into(), vec![], three, 5, 20.into(), None);
+        state.trash_file(&"/d".to_string(), 21.into(), None);
+        assert!(state.versions.is_empty());
+        assert_eq!(state.hash_size(&one), Some(3)); // 只被回收站中的历史版本引用的数据
+        let id = state.next_trash;
+        assert_eq!(state.trash_restore(id, None, UserId::anonymous()), Ok("/d".to_string()));
+        assert_eq!(
+            state.download_by("/d".into(), 0, 3, Some(1), 0.into()),
+            Ok(b"one".to_vec())
+        );
+        state.trash_file(&"/d".to_string(), 22.into(), None);
+        assert!(state.trash_empty(None).is_ok());
+        assert_eq!(state.trash_purge(22.into()), 1);
+        assert!(!state.assets.contains_key(&one));
+        assert_eq!(state.hashes.get(&three).map(|HashedPath(set)| set.len()), Some(1));
+    }
+
+    #[test]
+    fn should_expire_files() {
+        let mut state = InnerState::default();
+        let (one, two) = (HashDigest::from([1; 32]), HashDigest::from([2; 32]));
+        put_test_asset(&mut state, "/tmp/a", one, b"one");
+        put_test_asset(&mut state, "/tmp/b", two, b"two");
+        put_test_asset(&mut state, "/keep", two, b"two");
+        state.set_expires_at(&"/tmp/a".to_string(), Some(10.into()));
+        state.set_expires_at(&"/tmp/b".to_string(), Some(20.into()));
+
+        // 过期后不可访问
+        assert_eq!(state.download("/tmp/a".into(), 9.into()), Ok(b"one".to_vec()));
+        assert!(matches!(
+            state.download("/tmp/a".into(), 10.into()),
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(matches!(
+            state.download_by("/tmp/a".into(), 0, 1, None, 10.into()),
+            Err(StorageError::NotFound(_))
+        ));
+
+        // 定时任务清除过期的文件, 其他文件引用的数据保留
+        assert_eq!(state.files_expire(9.into()), Vec::<String>::new());
+        assert_eq!(
+            state.files_expire(20.into()),
+            vec!["/tmp/a".to_string(), "/tmp/b".to_string()]
+        );
+        assert!(!state.assets.contains_key(&one));
+        assert!(state.hashes.get(&one).is_none());
+        assert!(state.assets.contains_key(&two));
+        assert_eq!(state.download("/keep".into(), 20.into()), Ok(b"two".to_vec()));
+    }
+
+    #[test]
+    fn should_check_path_acl() {
+        let mut state = InnerState::default();
+        for path in ["/public/a", "/private/a", "/private/team/a"] {
+            put_test_asset(&mut state, path, HashDigest::default(), b"data");
+        }
+        let (anonymous, alice, bob, carol) = (
+            UserId::anonymous(),
+            UserId::from_slice(&[1]),
+            UserId::from_slice(&[2]),
+            UserId::from_slice(&[3]),
+        );
+        state
+            .canister_kit
+            .permissions
+            .user_roles
+            .insert(bob, ["team".to_string()].into_iter().collect());
+
+        let private = PathAcl {
+            read: AccessRule::Authenticated,
+            upload: None,
+            delete: None,
+        };
+        let team = PathAcl {
+            read: AccessRule::Restricted {
+                users: [alice].into_iter().collect(),
+                roles: ["team".to_string()].into_iter().collect(),
+            },
+            upload: Some(AccessRule::Restricted {
+                users: Default::default(),
+                roles: ["team".to_string()].into_iter().collect(),
+            }),
+            delete: None,
+        };
+        assert!(matches!(
+            state.acl_update("private".into(), Some(private.clone())),
+            Err(StorageError::InvalidPath(_))
+        ));
+        assert!(state.acl_update("/private/".into(), Some(private)).is_ok());
+        assert!(state.acl_update("/private/team/".into(), Some(team)).is_ok());
+
+        // 按最长匹配的前缀读取
+        assert!(state.path_permitted("/public/a", &anonymous, ACTION_BUSINESS_QUERY));
+        assert!(!state.path_permitted("/private/a", &anonymous, ACTION_BUSINESS_QUERY));
+        assert!(state.path_permitted("/private/a", &alice, ACTION_BUSINESS_QUERY));
+        assert!(state.path_permitted("/private/team/a", &bob, ACTION_BUSINESS_QUERY));
+        assert_eq!(state.files(&anonymous).len(), 1);
+        assert_eq!(state.files(&alice).len(), 3);
+
+        // 上传和删除的规则对拥有全局权限的用户也生效, 目录下的规则都要允许
+        assert!(!state.path_permitted("/public/a", &bob, ACTION_BUSINESS_UPLOAD));
+        assert!(state.path_permitted("/private/team/b", &bob, ACTION_BUSINESS_UPLOAD));
+        assert!(!state.path_permitted("/private/team/b", &alice, ACTION_BUSINESS_UPLOAD));
+        assert!(!state.path_permitted("/private/team/", &bob, ACTION_BUSINESS_DELETE));
+        assert!(!state.path_permitted("/private/", &carol, ACTION_BUSINESS_QUERY));
+        let upload = state.parse_permission(ACTION_BUSINESS_UPLOAD).unwrap();
+        state
+            .canister_kit
+            .permissions
+            .user_permissions
+            .insert(carol, [upload].into_iter().collect());
+        assert!(state.path_permitted("/public/a", &carol, ACTION_BUSINESS_UPLOAD));
+        assert!(state.path_permitted("/private/a", &carol, ACTION_BUSINESS_UPLOAD)); // 没有上传规则
+        assert!(!state.path_permitted("/private/team/a", &carol, ACTION_BUSINESS_UPLOAD)); // 规则不允许
+
+        assert!(state.acl_update("/private/team/".into(), None).is_ok());
+        assert_eq!(state.acl_list().len(), 1);
+        assert!(state.path_permitted("/private/", &carol, ACTION_BUSINESS_QUERY));
+    }
+
+    #[test]
+    fn should_account_quota() {
+        let mut state = InnerState::default();
+        let alice = UserId::from_slice(&[1]);
+        let bob = UserId::from_slice(&[2]);
+        let (hash, other) = (HashDigest([1; 32]), HashDigest([2; 32]));
+        state.put_file_at("/a".into(), vec![], hash, 100, 0.into(), Some(alice));
+        state.put_file_at("/b".into(), vec![], other, 50, 0.into(), Some(alice));
+        state.put_file_at("/c".into(), vec![], hash, 100, 0.into(), Some(bob)); // 相同的数据各自计算
+        let usage = |state: &InnerState, user: UserId| state.usage_query(user).usage;
+        assert_eq!(usage(&state, alice), StorageUsage { bytes: 150, files: 2 });
+        assert_eq!(usage(&state, bob), StorageUsage { bytes: 100, files: 1 });
+
+        // 覆盖别人的文件, 原来的内容保存为历史版本, 仍然计入原来的上传者
+        state.put_file_at("/c".into(), vec![], other, 50, 1.into(), Some(alice));
+        assert_eq!(usage(&state, alice), StorageUsage { bytes: 200, files: 3 });
+        assert_eq!(usage(&state, bob), StorageUsage { bytes: 100, files: 1 });
+        assert_eq!(
+            state.check_quota(&alice, &[("/a", 150)]),
+            Ok(()) // 没有配额不限制
+        );
+
+        // 历史版本清除后释放用量
+        let retention = VersionRetention {
+            max_versions: 0,
+            max_age: None,
+        };
+        assert!(state.version_retention_update(retention).is_ok());
+        assert_eq!(state.versions_expire(2.into()), 1);
+        assert!(!state.usage.contains_key(&bob));
+
+        // 用户自己的配额, 覆盖自己的文件扣除原来的大小
+        let quota = StorageQuota {
+            max_bytes: Some(250),
+            max_files: None,
+        };
+        assert!(state.quota_update(QuotaTarget::User(alice), Some(quota)).is_ok());
+        assert_eq!(
+            state.check_quota(&alice, &[("/d", 51)]),
+            Err(StorageError::QuotaExceeded { used: 251, limit: 250 })
+        );
+        assert_eq!(state.check_quota(&alice, &[("/a", 150)]), Ok(()));
+
+        // 上传中的文件也计入
+        state.uploading.insert(
+            "/u".to_string(),
+            UploadingFile {
+                session: 1,
+                path: "/u".to_string(),
+                headers: vec![],
+                hash: HashDigest::default(),
+                size: 30,
+                chunk_size: 30,
+                chunks: 1,
+                chunked: vec![false],
+                created: 0.into(),
+                touched: 0.into(),
+                expires_at: None,
+                uploader: Some(alice),
+                failed: None,
+            },
+        );
+        assert_eq!(state.usage_query(alice).uploading, StorageUsage { bytes: 30, files: 1 });
+        assert_eq!(
+            state.check_quota(&alice, &[("/d", 21)]),
+            Err(StorageError::QuotaExceeded { used: 251, limit: 250 })
+        );
+        assert_eq!(state.check_quota(&alice, &[("/u", 50)]), Ok(())); // 重新上传同一路径
+
+        // 角色的配额
+        state
+            .canister_kit
+            .permissions
+            .user_roles
+            .insert(bob, ["team".to_string()].into_iter().collect());
+        let quota = StorageQuota {
+            max_bytes: None,
+            max_files: Some(1),
+        };
+        assert!(
+            state
+                .quota_update(QuotaTarget::Role("team".into()), Some(quota))
+                .is_ok()
+        );
+        assert_eq!(state.usage_query(bob).quota, Some(quota));
+        assert_eq!(
+            state.copy_files("/a".into(), "/e".into(), Some(bob)),
+            Ok(vec!["/e".to_string()])
+        );
+        assert_eq!(usage(&state, bob), StorageUsage { bytes: 100, files: 1 });
+        assert_eq!(
+            state.copy_files("/b".into(), "/f".into(), Some(bob)),
+            Err(StorageError::QuotaExceeded { used: 2, limit: 1 })
+        );
+
+        // 回收站中的文件仍然计入, 恢复同样受配额限制, 并记在恢复者名下
+        state.trash_file(&"/b".to_string(), 2.into(), Some(alice));
+        assert_eq!(usage(&state, alice), StorageUsage { bytes: 200, files: 3 });
+        assert_eq!(
+            state.trash_restore(1, None, bob),
+            Err(StorageError::QuotaExceeded { used: 2, limit: 1 })
+        );
+        assert_eq!(state.trash_restore(1, None, alice), Ok("/b".to_string()));
+        assert_eq!(usage(&state, alice), StorageUsage { bytes: 200, files: 3 });
+
+        // 清除回收站和删除文件释放用量
+        state.trash_file(&"/b".to_string(), 3.into(), Some(alice));
+        assert_eq!(state.trash_empty(Some(vec![2])), Ok(()));
+        assert_eq!(usage(&state, alice), StorageUsage { bytes: 150, files: 2 });
+        state.clean_file(&"/a".to_string());
+        assert_eq!(usage(&state, alice), StorageUsage { bytes: 50, files: 1 });
+
+        // 保留历史版本时, 覆盖自己的文件不扣除原来的大小
+        assert!(state.version_retention_update(VersionRetention::default()).is_ok());
+        assert_eq!(
+            state.check_quota(&alice, &[("/c", 171)]),
+            Err(StorageError::QuotaExceeded { used: 251, limit: 250 })
+        );
+        state.put_file_at("/c".into(), vec![], hash, 100, 4.into(), Some(alice));
+        assert_eq!(usage(&state, alice), StorageUsage { bytes: 150, files: 2 });
+        state.trash_file(&"/e".to_string(), 4.into(), Some(bob));
+        assert_eq!(usage(&state, bob), StorageUsage { bytes: 100, files: 1 });
+
+        // 升级后可以从文件索引, 历史版本和回收站重建
+        let indexed = state.usage.clone();
+        state.usage.clear();
+        state.usage_indexed = false;
+        state.usage_reindex();
+        assert_eq!(state.usage, indexed);
+    }
+
+    #[test]
+    fn should_check_upload_token() {
+        let mut state = InnerState::default();
+        let issuer = UserId::from_slice(&[1]);
+        let arg = |prefix: &str, max_size: u64, expires_at: i128| UploadTokenArg {
+            prefix: prefix.to_string(),
+            max_size,
+            expires_at: expires_at.into(),
+        };
+        assert!(matches!(
+            state.upload_token_mint(arg("avatars/", 10, 100), [0; 32], 0.into(), issuer),
+            Err(StorageError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            state.upload_token_mint(arg("/avatars/", 10, 100), [0; 32], 100.into(), issuer),
+            Err(StorageError::InvalidArgument(_))
+        ));
+        let token = state.upload_token_mint(arg("/avatars/", 10, 100), [1; 32], 0.into(), issuer);
+        let token = token.unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(token.id, 1);
+        assert_eq!(state.upload_tokens().len(), 1);
+
+        let upload = |path: &str, size: u64| UploadingArg {
+            path: path.to_string(),
+            headers: vec![],
+            hash: HashDigest::default(),
+            size,
+            chunk_size: 10,
+            index: 0,
+            chunk: vec![0; size as usize],
+            expires_at: None,
+        };
+        assert_eq!(
+            state.upload_by_token("wrong", vec![upload("/avatars/a", 1)], 1.into()),
+            Err(StorageError::InvalidToken("upload token not found or expired".into()))
+        );
+        assert_eq!(
+            state.upload_by_token(&token.token, vec![upload("/avatars/a", 1)], 100.into()),
+            Err(StorageError::InvalidToken("upload token not found or expired".into()))
+        );
+        assert_eq!(
+            state.upload_by_token(&token.token, vec![upload("/other/a", 1)], 1.into()),
+            Err(StorageError::InvalidPath("path must start with /avatars/".into()))
+        );
+        assert_eq!(
+            state.upload_by_token(&token.token, vec![upload("/avatars/a", 11)], 1.into()),
+            Err(StorageError::TooLarge { size: 11, max: 10 })
+        );
+        assert_eq!(
+            state.upload_by_token(
+                &token.token,
+                vec![upload("/avatars/a", 1), upload("/avatars/b", 1)],
+                1.into()
+            ),
+            Err(StorageError::InvalidToken(
+                "token can only upload one file: /avatars/a".into()
+            ))
+        );
+        assert_eq!(state.upload_by_token(&token.token, vec![], 1.into()), Ok(1));
+        assert!(init_uploading_data().is_empty()); // 检查失败时不写入数据
+
+        // 前缀按目录匹配
+        let token = state.upload_token_mint(arg("/u/user1", 10, 100), [3; 32], 0.into(), issuer);
+        let token = token.unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            state.upload_by_token(&token.token, vec![upload("/u/user10/a", 1)], 1.into()),
+            Err(StorageError::InvalidPath("path must start with /u/user1/".into()))
+        );
+        assert!(state.upload_tokens().iter().any(|t| t.prefix == "/u/user1/"));
+
+        // 撤销和过期清除
+        assert_eq!(state.upload_token_revoke(1), Ok(()));
+        assert!(matches!(
+            state.upload_token_revoke(1),
+            Err(StorageError::InvalidToken(_))
+        ));
+        assert!(
+            state
+                .upload_token_mint(arg("/avatars/", 10, 100), [2; 32], 0.into(), issuer)
+                .is_ok()
+        );
+        state.upload_expire(100.into());
+        assert!(state.upload_tokens().is_empty());
+    }
+
+    #[test]
+    fn should_sign_url() {
+        // https://datatracker.ietf.org/doc/html/rfc4231#section-4.3
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        let secret = HashDigest([1; 32]);
+        let sig = url_signature(&secret, "/private/a b.txt", None, 10);
+        assert_eq!(
+            signed_url(&secret, "/private/a b.txt", None, 10),
+            format!("/private/a%20b.txt?sig={sig}&exp=10")
+        );
+        let verified = |path: &str, version: Option<u64>, sig: &str, exp: u64, now: i128| {
+            url_verified(&secret, path, version, sig, exp, now.into())
+        };
+        assert!(verified("/private/a b.txt", None, &sig, 10, 9_999_999_999));
+        assert!(!verified("/private/a b.txt", None, &sig, 10, 10_000_000_000)); // 过期
+        assert!(!verified("/private/b.txt", None, &sig, 10, 0)); // 路径不对
+        assert!(!verified("/private/a b.txt", None, &sig, 11, 0)); // 过期时间被修改
+        assert!(!verified("/private/a b.txt", Some(1), &sig, 10, 0)); // 不能加上版本访问历史版本
+        assert!(!url_verified(
+            &HashDigest([2; 32]),
+            "/private/a b.txt",
+            None,
+            &sig,
+            10,
+            0.into()
+        ));
+
+        // 指定历史版本的链接只能访问该版本
+        let sig = url_signature(&secret, "/a", Some(2), 10);
+        assert_eq!(
+            signed_url(&secret, "/a", Some(2), 10),
+            format!("/a?version=2&sig={sig}&exp=10")
+        );
+        assert!(verified("/a", Some(2), &sig, 10, 0));
+        assert!(!verified("/a", Some(1), &sig, 10, 0));
+        assert!(!verified("/a", None, &sig, 10, 0));
+    }
+}
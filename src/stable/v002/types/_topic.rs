@@ -0,0 +1,45 @@
+use std::str::FromStr;
+
+use ic_canister_kit::types::*;
+use strum::IntoEnumIterator;
+use strum_macros::{EnumIter, EnumString};
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, EnumIter, EnumString, strum_macros::Display)]
+pub enum RecordTopics {
+    // ! 新的权限类型从 0 开始
+    UploadFile = 0,      // 上传文件
+    DeleteFile = 1,      // 删除文件
+    ExpireUploading = 2, // 清除过期的上传
+    HashMismatched = 3,  // 后台校验发现数据与 hash 不一致
+    MoveFile = 4,        // 移动文件
+    CopyFile = 5,        // 复制文件
+    UpdateHeaders = 6,   // 更新文件的 headers
+    RestoreVersion = 7,  // 恢复历史版本
+    ExpireVersion = 8,   // 清除过期的历史版本
+    ExpireFile = 9,      // 清除过期的文件
+    UpdateQuota = 10,    // 设置存储配额
+    SignUrl = 11,        // 签发下载链接
+
+    // ! 系统倒序排列
+    CyclesCharge = 249, // 充值
+    Upgrade = 250,      // 升级
+    Schedule = 251,     // 定时任务
+    Record = 252,       // 记录
+    Permission = 253,   // 权限
+    Pause = 254,        // 维护
+    Initial = 255,      // 初始化
+}
+
+#[allow(unused)]
+impl RecordTopics {
+    pub fn topic(&self) -> RecordTopic {
+        *self as u8
+    }
+    pub fn topics() -> Vec<String> {
+        RecordTopics::iter().map(|x| x.to_string()).collect()
+    }
+    pub fn from(topic: &str) -> Result<Self, strum::ParseError> {
+        RecordTopics::from_str(topic)
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashSet;
+
+use candid::CandidType;
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use super::{ACTION_BUSINESS_DELETE, ACTION_BUSINESS_QUERY, ACTION_BUSINESS_UPLOAD};
+
+// ============================== 路径访问控制 ==============================
+
+// 最多的访问控制规则数量
+pub const MAX_ACL_RULES: usize = 1000;
+
+// 访问规则
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum AccessRule {
+    Public,        // 任何人, 包括匿名用户和 http 访问
+    Authenticated, // 非匿名用户
+    Restricted {
+        users: HashSet<UserId>,
+        roles: HashSet<String>, // 权限管理中的角色
+    },
+}
+
+impl AccessRule {
+    pub fn allows(&self, caller: &UserId, caller_roles: Option<&HashSet<String>>) -> bool {
+        match self {
+            AccessRule::Public => true,
+            AccessRule::Authenticated => *caller != candid::Principal::anonymous(),
+            AccessRule::Restricted { users, roles } => {
+                users.contains(caller) || caller_roles.is_some_and(|caller_roles| !caller_roles.is_disjoint(roles))
+            }
+        }
+    }
+}
+
+// 路径前缀的访问控制, 按最长匹配的前缀生效
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PathAcl {
+    pub read: AccessRule,           // 查询和下载, 包括 http 访问
+    pub upload: Option<AccessRule>, // 允许上传的用户, 设置后拥有上传权限的用户也要满足, 不设置则按全局权限
+    pub delete: Option<AccessRule>, // 允许删除的用户, 设置后拥有删除权限的用户也要满足, 不设置则按全局权限
+}
+
+impl PathAcl {
+    // 权限对应的规则
+    pub fn rule(&self, permission: &str) -> Option<&AccessRule> {
+        match permission {
+            ACTION_BUSINESS_QUERY => Some(&self.read),
+            ACTION_BUSINESS_UPLOAD => self.upload.as_ref(),
+            ACTION_BUSINESS_DELETE => self.delete.as_ref(),
+            _ => None,
+        }
+    }
+}
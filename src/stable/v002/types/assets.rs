@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+
+use candid::CandidType;
+use ic_canister_kit::common::trap;
+use ic_canister_kit::functions::stable::{from_bytes, to_bytes};
+use ic_canister_kit::stable::{Bound, Cow, Storable};
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use crate::stable::v002::types::init_assets_data;
+
+use super::{HashDigest, SliceOfHashDigest, UploadingFile};
+
+// ============================== 文件数据 ==============================
+
+// 单个文件数据
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct AssetData {
+    // 堆内存无数据，存放在稳定内存了
+}
+
+const MAX_BUCKET_SIZE: u64 = 1024 * 1024 * 2;
+
+#[inline]
+fn get_key(hash: &HashDigest, chunk: u32) -> SliceOfHashDigest {
+    let mut key = [0; 36];
+    key[..4].copy_from_slice(&chunk.to_be_bytes());
+    key[4..].copy_from_slice(&hash.0);
+    key
+}
+
+impl AssetData {
+    // 数据块的数量
+    pub fn buckets(data_size: u64) -> u32 {
+        data_size.div_ceil(MAX_BUCKET_SIZE) as u32
+    }
+    // 同步写入指定的数据块 起始块 包含, 末尾块 不包含
+    pub fn write<F>(hash: &HashDigest, data_size: u64, start: u32, end: u32, read: F)
+    where
+        F: Fn(usize, usize) -> Vec<u8>,
+    {
+        let mut assets = init_assets_data();
+        for i in start..end {
+            let offset = MAX_BUCKET_SIZE as usize * i as usize;
+            let offset_end = std::cmp::min(offset + MAX_BUCKET_SIZE as usize, data_size as usize);
+            assets.insert(get_key(hash, i), read(offset, offset_end));
+        }
+    }
+    // 删除该 hash 对应的所有数据块
+    pub fn remove(hash: &HashDigest, data_size: u64) {
+        let mut assets = init_assets_data();
+        for i in 0..Self::buckets(data_size) {
+            assets.remove(&get_key(hash, i));
+        }
+    }
+    // 从游标之后扫描有限的数据块, 删除没有被引用的, 返回删除的块数和下一批的游标
+    pub fn purge<F>(cursor: Option<&[u8]>, limit: usize, exists: F) -> (u64, Option<Vec<u8>>)
+    where
+        F: Fn(&HashDigest) -> bool,
+    {
+        use std::ops::Bound;
+        let mut assets = init_assets_data();
+        let start = match cursor.and_then(|cursor| SliceOfHashDigest::try_from(cursor).ok()) {
+            Some(cursor) => Bound::Excluded(cursor),
+            None => Bound::Unbounded,
+        };
+        let keys = assets
+            .keys_range((start, Bound::Unbounded))
+            .take(limit)
+            .collect::<Vec<_>>();
+        let orphans = keys
+            .iter()
+            .filter(|key| {
+                let mut hash = [0; 32];
+                hash.copy_from_slice(&key[4..]);
+                !exists(&HashDigest(hash))
+            })
+            .collect::<Vec<_>>();
+        for key in orphans.iter() {
+            assets.remove(key);
+        }
+        let next = match keys.last() {
+            Some(key) if keys.len() == limit => Some(key.to_vec()), // 可能还有没扫描的数据块
+            _ => None,
+        };
+        (orphans.len() as u64, next)
+    }
+    pub fn slice(&self, hash: &HashDigest, data_size: u64, offset: usize, size: usize) -> std::borrow::Cow<'_, [u8]> {
+        assert!(offset < data_size as usize);
+        let offset_end = offset + size;
+        assert!(offset_end <= data_size as usize);
+
+        let mut result = vec![0; size];
+        let mut cursor = 0;
+
+        let assets = init_assets_data();
+
+        let mut last_chunk = offset as u64 / MAX_BUCKET_SIZE;
+        let mut offset = (offset as u64 - last_chunk * MAX_BUCKET_SIZE) as usize;
+        let mut size = size;
+        while 0 < size {
+            let remain = MAX_BUCKET_SIZE as usize - offset; // 本次最多可以取这么多
+            let fetch = std::cmp::min(size, remain); // 本次应该取的数据
+
+            let key = get_key(hash, last_chunk as u32);
+
+            let data = assets.get(&key);
+            let data = ic_canister_kit::common::trap(data.ok_or("can not be"));
+
+            result[cursor..cursor + fetch].copy_from_slice(&data[offset..offset + fetch]);
+
+            cursor += fetch; // 修改结果写入位置
+            last_chunk += 1; // 修改为下一个块
+            offset = (offset + fetch) % MAX_BUCKET_SIZE as usize; // 修改并检查新的块偏移位置
+            size -= fetch; // 修改剩余的数据
+        }
+
+        std::borrow::Cow::Owned(result)
+    }
+    // 读取指定的数据块
+    pub fn bucket(hash: &HashDigest, chunk: u32) -> Option<Vec<u8>> {
+        init_assets_data().get(&get_key(hash, chunk))
+    }
+}
+
+// 每条消息最多写入的数据块数量, 剩余的数据在后续的消息中继续写入
+pub const MAX_COMMIT_BUCKETS: u32 = 128;
+
+// 正在写入稳定内存的数据
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssetCommit {
+    pub file: UploadingFile, // 待写入的数据, 暂存在稳定内存
+    pub written: u32,        // 已经写入的块数
+}
+
+impl AssetCommit {
+    pub fn new(file: UploadingFile) -> Self {
+        Self { file, written: 0 }
+    }
+    // 写入下一批数据块, 消耗写入额度, 返回是否已经全部写入
+    pub fn step(&mut self, hash: &HashDigest, budget: &mut u32) -> bool {
+        let buckets = AssetData::buckets(self.file.size);
+        let end = std::cmp::min(buckets, self.written + *budget);
+        AssetData::write(hash, self.file.size, self.written, end, |offset, offset_end| {
+            self.file.read(offset, offset_end)
+        });
+        *budget -= end - self.written;
+        self.written = end;
+        if self.written < buckets {
+            return false;
+        }
+        self.file.remove(); // 组装完成, 删除暂存的数据块
+        true
+    }
+}
+
+// 每条消息最多校验的数据块数量, 剩余的数据在后续的消息中继续校验
+pub const MAX_VERIFY_BUCKETS: u32 = 32;
+
+// 正在校验的数据, 重新计算 sha256
+pub struct AssetVerifier {
+    pub hash: HashDigest, // 上传时声明的 hash
+    size: u64,
+    read: u32, // 已经读取的块数
+    hasher: sha2::Sha256,
+}
+
+impl AssetVerifier {
+    pub fn new(hash: HashDigest, size: u64) -> Self {
+        use sha2::Digest;
+        Self {
+            hash,
+            size,
+            read: 0,
+            hasher: sha2::Sha256::new(),
+        }
+    }
+    // 读取下一批数据块, 消耗校验额度, 返回是否已经全部读取
+    pub fn step(&mut self, budget: &mut u32) -> bool {
+        use sha2::Digest;
+        let buckets = AssetData::buckets(self.size);
+        let end = std::cmp::min(buckets, self.read + *budget);
+        for i in self.read..end {
+            let data = trap(AssetData::bucket(&self.hash, i).ok_or("can not be"));
+            self.hasher.update(&data);
+        }
+        *budget -= end - self.read;
+        self.read = end;
+        self.read == buckets
+    }
+    // 数据是否与 hash 一致
+    pub fn matched(self) -> bool {
+        use sha2::Digest;
+        let digest: [u8; 32] = self.hasher.finalize().into();
+        digest == self.hash.0
+    }
+}
+
+// 每条消息最多扫描的文件数量, 剩余的文件在后续的消息中继续删除
+pub const MAX_DELETE_FILES: u32 = 512;
+
+// 每条消息最多扫描的数据块数量, 剩余的数据块在后续的消息中继续清除
+pub const MAX_PURGE_BUCKETS: u32 = 4096;
+
+// 正在清除没有被引用的数据块
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OrphanPurge {
+    pub cursor: Option<Vec<u8>>, // 已经扫描到的数据块的键
+}
+
+// 正在删除的目录
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeletingDir {
+    pub prefix: String,         // 以 / 结尾的目录路径
+    pub recursive: bool,        // 是否删除子目录
+    pub cursor: Option<String>, // 已经扫描到的路径
+    #[serde(default)]
+    pub deleter: Option<UserId>, // 删除的文件放入回收站时记录
+}
+
+impl DeletingDir {
+    pub fn new(prefix: String, recursive: bool, deleter: Option<UserId>) -> Self {
+        Self {
+            prefix,
+            recursive,
+            cursor: None,
+            deleter,
+        }
+    }
+    // 路径是否属于要删除的目录
+    pub fn contains(&self, path: &str) -> bool {
+        match path.strip_prefix(&self.prefix) {
+            Some(rest) => self.recursive || !rest.contains('/'),
+            None => false,
+        }
+    }
+}
+
+// 一次移动, 复制或者更新 headers 的最大文件数量, 需要在一条消息内完成
+pub const MAX_BATCH_FILES: u32 = 1000;
+
+// 文件状态
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AssetStatus {
+    #[default]
+    Committed, // 数据已经完整写入稳定内存
+    Committing, // 数据还在写入稳定内存, 暂不可访问
+    Mismatched, // 引用的数据没有校验过并且已被替换, 需要重新上传或者证明持有数据
+}
+
+// 对外的路径数据 指向文件数据
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct AssetFile {
+    pub path: String,
+    pub created: TimestampNanos,
+    pub modified: TimestampNanos,
+    pub headers: Vec<(String, String)>,
+    pub hash: HashDigest,
+    pub size: u64,
+    #[serde(default)]
+    pub status: AssetStatus,
+    #[serde(default)]
+    pub uploader: Option<UserId>, // 上传者, 之前的版本没有记录
+    #[serde(default)]
+    pub expires_at: Option<TimestampNanos>, // 过期时间, 过期后不可访问, 由定时任务清除
+}
+
+impl AssetFile {
+    // 是否已经过期
+    pub fn expired(&self, now: TimestampNanos) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at.into_inner() <= now.into_inner())
+    }
+    // 缓存标识, 内容和 headers 任意一个变化都会改变
+    pub fn etag(&self) -> String {
+        if self.headers.is_empty() {
+            return self.hash.hex();
+        }
+        use sha2::Digest;
+        let mut hasher = sha2::Sha256::new();
+        for (name, value) in self.headers.iter() {
+            hasher.update((name.len() as u64).to_be_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update((value.len() as u64).to_be_bytes());
+            hasher.update(value.as_bytes());
+        }
+        let digest: [u8; 32] = hasher.finalize().into();
+        format!("{}-{}", self.hash.hex(), hex::encode(&digest[..8]))
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HashedPath(pub(super) HashSet<String>);
+
+// ============================== 稳定内存存储 ==============================
+
+impl Storable for AssetData {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&[])
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn from_bytes(_bytes: Cow<[u8]>) -> Self {
+        AssetData {}
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 0,
+        is_fixed_size: true,
+    };
+}
+
+impl Storable for AssetFile {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(trap(to_bytes(self)))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        trap(to_bytes(&self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        trap(from_bytes(&bytes))
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for HashedPath {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(trap(to_bytes(self)))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        trap(to_bytes(&self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        trap(from_bytes(&bytes))
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
@@ -0,0 +1,110 @@
+use candid::CandidType;
+use ic_canister_kit::stable::{Bound, Cow, Storable};
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use super::{AssetFile, AssetStatus};
+
+pub type SliceOfHashDigest = [u8; 4 + 32];
+pub type SliceOfUploading = [u8; 32 + 4];
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HashDigest(pub(super) [u8; 32]);
+
+impl HashDigest {
+    pub fn hex(&self) -> String {
+        hex::encode(self.0)
+    }
+    pub fn digest(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl From<[u8; 32]> for HashDigest {
+    fn from(value: [u8; 32]) -> Self {
+        Self(value)
+    }
+}
+
+impl Storable for HashDigest {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&bytes);
+        Self(digest)
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 32,
+        is_fixed_size: true,
+    };
+}
+
+// =========== 查询的对象 ===========
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct QueryFile {
+    pub path: String,
+    pub size: u64,
+    pub headers: Vec<(String, String)>,
+    pub created: TimestampNanos,
+    pub modified: TimestampNanos,
+    pub hash: String,
+    pub status: AssetStatus,
+    pub expires_at: Option<TimestampNanos>,
+}
+
+impl From<AssetFile> for QueryFile {
+    fn from(file: AssetFile) -> Self {
+        QueryFile {
+            path: file.path,
+            size: file.size,
+            headers: file.headers,
+            created: file.created,
+            modified: file.modified,
+            hash: file.hash.hex(),
+            status: file.status,
+            expires_at: file.expires_at,
+        }
+    }
+}
+
+// 目录下的子目录, 不统计子树, 避免遍历整个子树
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct QueryDirEntry {
+    pub name: String,
+}
+
+// 目录的直接子项, 按路径的字典序分页
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct QueryDir {
+    pub path: String, // 以 / 结尾的目录路径
+    pub dirs: Vec<QueryDirEntry>,
+    pub files: Vec<QueryFile>,
+    pub next: Option<String>, // 下一页的游标, 没有更多数据时为空
+}
+
+// 分页查询文件的最大页面大小
+pub const MAX_FILES_PAGE_SIZE: u32 = 1000;
+
+// 文件排序方式, 按路径的字典序
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileSort {
+    #[default]
+    PathAsc,
+    PathDesc,
+}
+
+// 分页查询文件的结果
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct QueryFilePage {
+    pub page: PageData<QueryFile>,
+    pub next: Option<String>, // 下一页的游标, 没有更多数据时为空
+}
@@ -0,0 +1,74 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// =========== 业务错误 ===========
+
+// 业务接口返回的错误, 旧接口直接 trap 错误信息
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    NotFound(String),                                               // 文件不存在, 内容是路径
+    AlreadyExists(String),                                          // 目标文件已经存在, 内容是路径
+    SessionNotFound(u64),                                           // 上传会话不存在
+    ChallengeNotFound,                                              // 持有证明的挑战不存在或者已经使用
+    InvalidPath(String),                                            // 路径不合法, 内容是原因
+    InvalidHeader(String),                                          // header 不合法, 内容是 header 名称
+    InvalidArgument(String),                                        // 其他参数不合法, 内容是原因
+    TooLarge { size: u64, max: u64 },                               // 文件太大
+    OutOfRange { offset: u64, size: u64, total: u64 },              // 读取的范围超出文件长度
+    ChunkIndexOutOfRange { index: u32, chunks: u32 },               // 数据块序号超出范围
+    ChunkLengthMismatch { index: u32, expected: u64, actual: u64 }, // 数据块长度不对
+    UploadNotFinished { uploaded: u32, chunks: u32 },               // 还有数据块没有上传
+    SizeMismatch { expected: u64, actual: u64 },                    // 上传的数据长度与声明的不一致
+    HashMismatch { expected: String, actual: String },              // 上传的数据 hash 与声明的不一致
+    ProofMismatch,                                                  // 持有证明的回答错误
+    Committing(String),                                             // 该路径上次上传的数据还在写入
+    QuotaExceeded { used: u64, limit: u64 },                        // 超出存储配额
+    InvalidToken(String),                                           // 凭证无效, 内容是原因
+    Paused(String),                                                 // 维护中
+    PermissionDenied(String),                                       // 缺少权限, 内容是权限名称
+    Internal(String),                                               // 系统错误
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ! 旧接口 trap 的信息保持不变
+        match self {
+            StorageError::NotFound(path) => write!(f, "File not found: {path}"),
+            StorageError::AlreadyExists(path) => write!(f, "File already exists: {path}"),
+            StorageError::SessionNotFound(session) => write!(f, "session not found: {session}"),
+            StorageError::ChallengeNotFound => write!(f, "challenge not found"),
+            StorageError::InvalidPath(reason) => write!(f, "{reason}"),
+            StorageError::InvalidHeader(name) => write!(f, "header is too large: {name}"),
+            StorageError::InvalidArgument(reason) => write!(f, "{reason}"),
+            StorageError::TooLarge { size, max } => write!(f, "size must less than {max}: {size}"),
+            StorageError::OutOfRange { offset, size, total } => {
+                write!(f, "out of range: offset: {offset} size: {size} total: {total}")
+            }
+            StorageError::ChunkIndexOutOfRange { index, chunks } => write!(f, "wrong index: {index} >= {chunks}"),
+            StorageError::ChunkLengthMismatch {
+                index,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "wrong chunk length: index: {index} expected: {expected} actual: {actual}"
+                )
+            }
+            StorageError::UploadNotFinished { uploaded, chunks } => {
+                write!(f, "upload is not finished: {uploaded}/{chunks}")
+            }
+            StorageError::SizeMismatch { expected, actual } => write!(f, "wrong size: {expected} != {actual}"),
+            StorageError::HashMismatch { expected, actual } => write!(f, "wrong hash: {expected} != {actual}"),
+            StorageError::ProofMismatch => write!(f, "wrong proof"),
+            StorageError::Committing(path) => write!(f, "file is committing, try again later: {path}"),
+            StorageError::QuotaExceeded { used, limit } => write!(f, "quota exceeded: {used} > {limit}"),
+            StorageError::InvalidToken(reason) => write!(f, "invalid token: {reason}"),
+            StorageError::Paused(reason) => write!(f, "{reason}"),
+            StorageError::PermissionDenied(permission) => write!(f, "Permission '{permission}' is required"),
+            StorageError::Internal(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
@@ -0,0 +1,121 @@
+use std::collections::{HashMap, HashSet};
+
+use candid::CandidType;
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use super::StorageError;
+
+// ============================== 存储配额 ==============================
+
+// 用户占用的存储, 按文件统计, 历史版本和回收站中的文件也计入
+// 相同内容的文件各自计算大小, 否则引用别人已经上传的数据不占配额, 删除的先后也会改变各自的用量
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub bytes: u64, // 文件大小之和
+    pub files: u64, // 文件数量
+}
+
+impl StorageUsage {
+    pub fn add(&mut self, size: u64) {
+        self.bytes = self.bytes.saturating_add(size);
+        self.files = self.files.saturating_add(1);
+    }
+    pub fn sub(&mut self, size: u64) {
+        self.bytes = self.bytes.saturating_sub(size);
+        self.files = self.files.saturating_sub(1);
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bytes == 0 && self.files == 0
+    }
+}
+
+// 存储配额, 不设置的项不限制
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageQuota {
+    pub max_bytes: Option<u64>,
+    pub max_files: Option<u64>,
+}
+
+impl StorageQuota {
+    // 用量是否在配额内
+    pub fn check(&self, usage: &StorageUsage) -> Result<(), StorageError> {
+        if let Some(limit) = self.max_bytes
+            && limit < usage.bytes
+        {
+            return Err(StorageError::QuotaExceeded {
+                used: usage.bytes,
+                limit,
+            });
+        }
+        if let Some(limit) = self.max_files
+            && limit < usage.files
+        {
+            return Err(StorageError::QuotaExceeded {
+                used: usage.files,
+                limit,
+            });
+        }
+        Ok(())
+    }
+    // 多个角色的配额取最宽松的
+    fn loosest(self, other: Self) -> Self {
+        let loosest = |a: Option<u64>, b: Option<u64>| a.zip(b).map(|(a, b)| a.max(b));
+        Self {
+            max_bytes: loosest(self.max_bytes, other.max_bytes),
+            max_files: loosest(self.max_files, other.max_files),
+        }
+    }
+}
+
+// 配额的设置对象
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum QuotaTarget {
+    User(UserId),
+    Role(String), // 权限管理中的角色
+}
+
+// 所有的配额设置
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StorageQuotas {
+    pub users: HashMap<UserId, StorageQuota>,
+    pub roles: HashMap<String, StorageQuota>,
+}
+
+impl StorageQuotas {
+    // 用户自己的配额优先, 否则取所属角色中最宽松的, 都没有则不限制
+    pub fn quota(&self, user: &UserId, roles: Option<&HashSet<String>>) -> Option<StorageQuota> {
+        if let Some(quota) = self.users.get(user) {
+            return Some(*quota);
+        }
+        roles?
+            .iter()
+            .filter_map(|role| self.roles.get(role).copied())
+            .reduce(StorageQuota::loosest)
+    }
+    pub fn update(&mut self, target: QuotaTarget, quota: Option<StorageQuota>) {
+        match (target, quota) {
+            (QuotaTarget::User(user), Some(quota)) => {
+                self.users.insert(user, quota);
+            }
+            (QuotaTarget::User(user), None) => {
+                self.users.remove(&user);
+            }
+            (QuotaTarget::Role(role), Some(quota)) => {
+                self.roles.insert(role, quota);
+            }
+            (QuotaTarget::Role(role), None) => {
+                self.roles.remove(&role);
+            }
+        }
+    }
+}
+
+// 对外的用量
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QueryUsage {
+    pub user: UserId,
+    pub usage: StorageUsage,
+    pub uploading: StorageUsage, // 还没有上传完成的文件
+    pub quota: Option<StorageQuota>,
+}
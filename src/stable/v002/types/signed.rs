@@ -0,0 +1,84 @@
+use candid::CandidType;
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use super::HashDigest;
+
+// ============================== 签名链接 ==============================
+
+// 签名链接最长的有效时间 7 天
+pub const MAX_SIGNED_URL_TTL: u128 = 1_000_000_000 * 60 * 60 * 24 * 7;
+
+// 链接中的路径需要编码的字符
+const PATH_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+// 签名后的链接
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignedUrl {
+    pub url: String,                // 相对路径, 带有 sig 和 exp 参数, 指定历史版本时还有 version 参数
+    pub expires_at: TimestampNanos, // 链接的过期时间, 精确到秒
+}
+
+// HMAC-SHA256 https://datatracker.ietf.org/doc/html/rfc2104, 密钥不超过 64 字节
+pub(super) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let pad = |byte: u8| {
+        let mut block = [byte; 64];
+        for (b, k) in block.iter_mut().zip(key) {
+            *b ^= k;
+        }
+        block
+    };
+    let mut inner = Sha256::new();
+    inner.update(pad(0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(pad(0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+// 对路径, 历史版本和过期时间(秒)签名, 路径以 / 开头, 两种格式不会混淆
+pub fn url_signature(secret: &HashDigest, path: &str, version: Option<u64>, exp: u64) -> String {
+    let message = match version {
+        Some(version) => format!("{exp}:{version}:{path}"),
+        None => format!("{exp}:{path}"), // 不指定版本的链接只能访问当前的内容
+    };
+    hex::encode(hmac_sha256(&secret.0, message.as_bytes()))
+}
+
+// 校验签名, 比较时间与内容无关
+pub fn url_verified(
+    secret: &HashDigest,
+    path: &str,
+    version: Option<u64>,
+    sig: &str,
+    exp: u64,
+    now: TimestampNanos,
+) -> bool {
+    if (exp as i128) * 1_000_000_000 <= now.into_inner() {
+        return false; // 已经过期
+    }
+    let expected = url_signature(secret, path, version, exp);
+    expected.len() == sig.len() && expected.bytes().zip(sig.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+// 签名后的相对链接
+pub fn signed_url(secret: &HashDigest, path: &str, version: Option<u64>, exp: u64) -> String {
+    let sig = url_signature(secret, path, version, exp);
+    let path = percent_encoding::utf8_percent_encode(path, PATH_ENCODE_SET);
+    match version {
+        Some(version) => format!("{path}?version={version}&sig={sig}&exp={exp}"),
+        None => format!("{path}?sig={sig}&exp={exp}"),
+    }
+}
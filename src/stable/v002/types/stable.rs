@@ -0,0 +1,42 @@
+use ic_canister_kit::stable;
+use ic_canister_kit::types::*;
+
+use super::{
+    AssetData, AssetFile, FileVersions, HashDigest, HashedPath, SliceOfHashDigest, SliceOfUploading, TrashedFile,
+};
+
+const MEMORY_ID_ASSETS: MemoryId = MemoryId::new(0); // 存放实际文件，hash 为键
+const MEMORY_ID_UPLOADING: MemoryId = MemoryId::new(1); // 存放上传中的数据块，路径和块序号为键
+const MEMORY_ID_FILES: MemoryId = MemoryId::new(2); // 存放文件索引，path 为键
+const MEMORY_ID_HASHES: MemoryId = MemoryId::new(3); // 存放 hash 对应的路径，hash 为键
+const MEMORY_ID_ASSETS_INDEX: MemoryId = MemoryId::new(4); // 存放已经写入完成的数据，hash 为键
+const MEMORY_ID_VERSIONS: MemoryId = MemoryId::new(5); // 存放文件的历史版本，path 为键
+const MEMORY_ID_TRASH: MemoryId = MemoryId::new(6); // 存放回收站中的文件，删除序号为键
+
+pub(super) fn init_assets_data() -> StableBTreeMap<SliceOfHashDigest, Vec<u8>> {
+    stable::init_map_data(MEMORY_ID_ASSETS)
+}
+
+pub(super) fn init_uploading_data() -> StableBTreeMap<SliceOfUploading, Vec<u8>> {
+    stable::init_map_data(MEMORY_ID_UPLOADING)
+}
+
+pub(super) fn init_files_data() -> StableBTreeMap<String, AssetFile> {
+    stable::init_map_data(MEMORY_ID_FILES)
+}
+
+pub(super) fn init_hashes_data() -> StableBTreeMap<HashDigest, HashedPath> {
+    stable::init_map_data(MEMORY_ID_HASHES)
+}
+
+pub(super) fn init_assets_index_data() -> StableBTreeMap<HashDigest, AssetData> {
+    stable::init_map_data(MEMORY_ID_ASSETS_INDEX)
+}
+
+pub(super) fn init_versions_data() -> StableBTreeMap<String, FileVersions> {
+    stable::init_map_data(MEMORY_ID_VERSIONS)
+}
+
+pub(super) fn init_trash_data() -> StableBTreeMap<u64, TrashedFile> {
+    stable::init_map_data(MEMORY_ID_TRASH)
+}
@@ -0,0 +1,72 @@
+use candid::CandidType;
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use super::HashDigest;
+
+// ============================== 上传凭证 ==============================
+
+// 最多同时有效的上传凭证数量
+pub const MAX_UPLOAD_TOKENS: usize = 10_000;
+
+// 签发上传凭证的参数
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadTokenArg {
+    pub prefix: String,             // 只能上传到该目录下, 不以 / 结尾时自动补上
+    pub max_size: u64,              // 文件的最大长度
+    pub expires_at: TimestampNanos, // 凭证的过期时间
+}
+
+// 签发的上传凭证, 只保存凭证的 hash
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadTokenData {
+    pub id: u64,
+    pub prefix: String,
+    pub max_size: u64,
+    pub expires_at: TimestampNanos,
+    pub issuer: UserId,       // 签发者, 上传的文件和配额记在签发者名下
+    pub path: Option<String>, // 只能上传一个文件, 第一次上传时绑定路径
+}
+
+impl UploadTokenData {
+    pub fn expired(&self, now: TimestampNanos) -> bool {
+        self.expires_at.into_inner() <= now.into_inner()
+    }
+}
+
+// 凭证在状态中的键, 泄露状态数据也不能伪造凭证
+pub fn token_hash(token: &str) -> HashDigest {
+    use sha2::Digest;
+    HashDigest(sha2::Sha256::digest(token.as_bytes()).into())
+}
+
+// 签发成功后返回给调用者, 凭证只出现这一次
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadToken {
+    pub id: u64,
+    pub token: String,
+}
+
+// 对外的上传凭证, 不包括凭证本身
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct QueryUploadToken {
+    pub id: u64,
+    pub prefix: String,
+    pub max_size: u64,
+    pub expires_at: TimestampNanos,
+    pub issuer: UserId,
+    pub path: Option<String>,
+}
+
+impl From<&UploadTokenData> for QueryUploadToken {
+    fn from(data: &UploadTokenData) -> Self {
+        QueryUploadToken {
+            id: data.id,
+            prefix: data.prefix.clone(),
+            max_size: data.max_size,
+            expires_at: data.expires_at,
+            issuer: data.issuer,
+            path: data.path.clone(),
+        }
+    }
+}
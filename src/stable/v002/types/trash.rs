@@ -0,0 +1,84 @@
+use candid::CandidType;
+use ic_canister_kit::common::trap;
+use ic_canister_kit::functions::stable::{from_bytes, to_bytes};
+use ic_canister_kit::stable::{Bound, Cow, Storable};
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use super::{AssetFile, FileVersions, QueryFile};
+
+// ============================== 回收站 ==============================
+
+// 默认的回收站保留时间 7 天
+pub const DEFAULT_TRASH_RETENTION: u128 = 1_000_000_000 * 60 * 60 * 24 * 7;
+
+#[inline]
+pub(super) fn default_trash_retention() -> DurationNanos {
+    DEFAULT_TRASH_RETENTION.into()
+}
+
+// 被删除的文件
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct TrashedFile {
+    pub id: u64,
+    pub file: AssetFile, // 删除前的文件
+    pub deleted: TimestampNanos,
+    pub deleter: Option<UserId>,
+    #[serde(default)]
+    pub versions: FileVersions, // 删除前的历史版本, 恢复时一并恢复
+}
+
+impl TrashedFile {
+    // 超过保留时间, 可以彻底清除
+    pub fn expired(&self, now: TimestampNanos, retention: DurationNanos) -> bool {
+        (retention.into_inner() as i128) < now.into_inner() - self.deleted.into_inner()
+    }
+}
+
+// 回收站中的文件在 hashes 中的引用, 不以 / 开头, 不会和文件路径冲突
+pub fn trash_ref(id: u64) -> String {
+    format!("trash:{id}")
+}
+
+// 回收站中的文件的历史版本在 hashes 中的引用
+pub fn trash_version_ref(id: u64, version: u64) -> String {
+    format!("trash:{id}:{version}")
+}
+
+// 对外的回收站文件
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct QueryTrashedFile {
+    pub id: u64,
+    pub file: QueryFile,
+    pub deleted: TimestampNanos,
+    pub deleter: Option<UserId>,
+}
+
+impl From<TrashedFile> for QueryTrashedFile {
+    fn from(trashed: TrashedFile) -> Self {
+        QueryTrashedFile {
+            id: trashed.id,
+            file: QueryFile::from(trashed.file),
+            deleted: trashed.deleted,
+            deleter: trashed.deleter,
+        }
+    }
+}
+
+// ============================== 稳定内存存储 ==============================
+
+impl Storable for TrashedFile {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(trap(to_bytes(self)))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        trap(to_bytes(&self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        trap(from_bytes(&bytes))
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
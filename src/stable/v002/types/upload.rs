@@ -0,0 +1,282 @@
+use candid::CandidType;
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use crate::stable::v002::types::init_uploading_data;
+
+use super::{HashDigest, SliceOfUploading, StorageError};
+
+// =========== 上传过程中的对象 ===========
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadingFile {
+    pub session: u64, // 上传会话
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub hash: HashDigest, // hash 值，在 hashed 为 false 的情况下不使用
+
+    pub size: u64,          // 文件大小
+    pub chunk_size: u32,    // 块大小 块分割的大小
+    pub chunks: u32,        // 需要上传的次数
+    pub chunked: Vec<bool>, // 记录每一个块的上传状态
+
+    pub created: TimestampNanos, // 开始上传的时间
+    pub touched: TimestampNanos, // 最后一次上传数据的时间
+
+    #[serde(default)]
+    pub expires_at: Option<TimestampNanos>, // 文件的过期时间
+    #[serde(default)]
+    pub uploader: Option<UserId>, // 开始上传的用户, 用于统计配额
+    #[serde(default)]
+    pub failed: Option<StorageError>, // 上次提交时后台校验失败的原因
+}
+
+// 最大文件 2G
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024 * 2;
+
+// 最小分块 64K, 只有一块时可以更小, 避免过多的分块
+pub const MIN_CHUNK_SIZE: u32 = 1024 * 64;
+
+// 默认的上传会话过期时间 1 天
+pub const DEFAULT_UPLOADING_TIMEOUT: u128 = 1_000_000_000 * 60 * 60 * 24;
+
+#[inline]
+fn get_key(path: &str, index: u32) -> SliceOfUploading {
+    use sha2::Digest;
+    let digest: [u8; 32] = sha2::Sha256::digest(path.as_bytes()).into();
+    let mut key = [0; 36];
+    key[..32].copy_from_slice(&digest);
+    key[32..].copy_from_slice(&index.to_be_bytes());
+    key
+}
+
+impl UploadingFile {
+    // 数据块的位置 起始 包含, 末尾 不包含
+    fn chunk_range(&self, index: u32) -> (usize, usize) {
+        let offset = self.chunk_size as u64 * index as u64;
+        let offset_end = std::cmp::min(offset + self.chunk_size as u64, self.size);
+        (offset as usize, offset_end as usize)
+    }
+    // 写入上传的数据块
+    pub fn write(&self, index: u32, chunk: Vec<u8>) {
+        let mut uploading = init_uploading_data();
+        uploading.insert(get_key(&self.path, index), chunk);
+    }
+    // 读取暂存的数据 起始 包含, 末尾 不包含
+    pub fn read(&self, offset: usize, offset_end: usize) -> Vec<u8> {
+        assert!(offset <= offset_end && offset_end <= self.size as usize);
+
+        let mut result = Vec::with_capacity(offset_end - offset);
+
+        let uploading = init_uploading_data();
+
+        let mut index = (offset / self.chunk_size as usize) as u32;
+        while result.len() < offset_end - offset {
+            let (start, end) = self.chunk_range(index);
+            let data = uploading.get(&get_key(&self.path, index));
+            let data = ic_canister_kit::common::trap(data.ok_or("can not be"));
+
+            let from = std::cmp::max(start, offset) - start;
+            let to = std::cmp::min(end, offset_end) - start;
+            result.extend_from_slice(&data[from..to]);
+
+            index += 1;
+        }
+
+        result
+    }
+    // 检查并写入上传的数据块
+    pub fn put(&mut self, index: u32, chunk: Vec<u8>) -> Result<(), StorageError> {
+        // 5. 检查 index
+        if self.chunks <= index {
+            return Err(StorageError::ChunkIndexOutOfRange {
+                index,
+                chunks: self.chunks,
+            });
+        }
+        // 6. 检查 data
+        let (offset, offset_end) = self.chunk_range(index);
+        if chunk.len() != offset_end - offset {
+            return Err(StorageError::ChunkLengthMismatch {
+                index,
+                expected: (offset_end - offset) as u64,
+                actual: chunk.len() as u64,
+            });
+        }
+
+        self.write(index, chunk);
+        self.chunked[index as usize] = true;
+        Ok(())
+    }
+    // 是否长时间没有上传数据了
+    pub fn expired(&self, now: TimestampNanos, timeout: DurationNanos) -> bool {
+        self.touched.into_inner() + (timeout.into_inner() as i128) < now.into_inner()
+    }
+    // 上传进度
+    pub fn status(&self) -> UploadingStatus {
+        let uploaded = (0..self.chunks)
+            .filter(|index| self.chunked[*index as usize])
+            .map(|index| {
+                let (offset, offset_end) = self.chunk_range(index);
+                (offset_end - offset) as u64
+            })
+            .sum();
+        UploadingStatus {
+            session: self.session,
+            path: self.path.clone(),
+            size: self.size,
+            chunk_size: self.chunk_size,
+            chunks: self.chunks,
+            chunked: self.chunked.clone(),
+            uploaded,
+            created: self.created,
+            touched: self.touched,
+            committing: false,
+            failed: self.failed.clone(),
+        }
+    }
+    // 删除暂存的数据块
+    pub fn remove(&self) {
+        let mut uploading = init_uploading_data();
+        for index in 0..self.chunks {
+            uploading.remove(&get_key(&self.path, index));
+        }
+    }
+}
+
+// 每条消息最多计算 hash 的数据量, 剩余的数据在后续的消息中继续计算
+pub const MAX_DIGEST_SIZE: u64 = 1024 * 1024 * 64;
+
+// 正在计算 hash 的暂存数据
+#[derive(Default)]
+pub struct UploadDigester {
+    read: u32, // 已经读取的块数
+    size: u64, // 已经读取的长度
+    hasher: sha2::Sha256,
+}
+
+impl UploadDigester {
+    // 读取下一批数据块, 消耗计算额度, 返回是否已经全部读取
+    pub fn step(&mut self, file: &UploadingFile, budget: &mut u64) -> bool {
+        use sha2::Digest;
+        let uploading = init_uploading_data();
+        while self.read < file.chunks && 0 < *budget {
+            let data = uploading.get(&get_key(&file.path, self.read));
+            let data = ic_canister_kit::common::trap(data.ok_or("can not be"));
+            self.hasher.update(&data);
+            self.size += data.len() as u64;
+            *budget = budget.saturating_sub(data.len() as u64);
+            self.read += 1;
+        }
+        self.read == file.chunks
+    }
+    // 暂存数据的 hash 和实际长度
+    pub fn finish(self) -> (HashDigest, u64) {
+        use sha2::Digest;
+        (HashDigest(self.hasher.finalize().into()), self.size)
+    }
+}
+
+// 上传参数
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadingArg {
+    pub path: String,
+    pub headers: Vec<(String, String)>,     // 使用的 header
+    pub hash: HashDigest,                   // hash 值，在 hashed 为 false 的情况下不使用
+    pub size: u64,                          // 文件大小
+    pub chunk_size: u32,                    // 块大小 块分割的大小
+    pub index: u32,                         // 本次上传的数据
+    pub chunk: Vec<u8>,                     // 上传中的数据
+    pub expires_at: Option<TimestampNanos>, // 过期时间, 不指定则一直保留
+}
+
+// 开始上传的参数
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadBeginArg {
+    pub path: String,
+    pub headers: Vec<(String, String)>,     // 使用的 header
+    pub hash: HashDigest,                   // hash 值，提交时会校验
+    pub size: u64,                          // 文件大小
+    pub chunk_size: u32,                    // 块大小 块分割的大小
+    pub expires_at: Option<TimestampNanos>, // 过期时间, 不指定则一直保留
+}
+
+// 上传数据块的参数
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadChunkArg {
+    pub session: u64,   // 上传会话
+    pub index: u32,     // 本次上传的数据
+    pub chunk: Vec<u8>, // 上传中的数据
+}
+
+// 上传进度
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadingStatus {
+    pub session: u64,
+    pub path: String,
+    pub size: u64,          // 文件大小
+    pub chunk_size: u32,    // 块大小 块分割的大小
+    pub chunks: u32,        // 需要上传的次数
+    pub chunked: Vec<bool>, // 记录每一个块的上传状态
+    pub uploaded: u64,      // 已经上传的数据长度
+    pub created: TimestampNanos,
+    pub touched: TimestampNanos,
+    pub committing: bool,             // 已经提交, 正在后台校验
+    pub failed: Option<StorageError>, // 上次提交时后台校验失败的原因, 可以重新上传数据块后再提交
+}
+
+// 提交上传的结果
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum UploadCommitted {
+    Committed(String), // 校验完成, 数据开始写入, 内容是路径
+    Pending(String),   // 数据较多, 正在后台校验, 通过上传进度查询结果
+}
+
+// =========== 持有证明 ===========
+
+// 已存在的数据不再上传时, 需要证明持有该数据, 每次挑战的数据长度
+pub const MAX_CHALLENGE_SIZE: u64 = 1024 * 1024;
+
+// 等待证明的挑战
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadChallengeData {
+    pub hash: HashDigest, // 要证明持有的数据
+    pub offset: u64,
+    pub size: u64,
+    pub created: TimestampNanos,
+}
+
+impl UploadChallengeData {
+    // 是否已经过期
+    pub fn expired(&self, now: TimestampNanos, timeout: DurationNanos) -> bool {
+        self.created.into_inner() + (timeout.into_inner() as i128) < now.into_inner()
+    }
+}
+
+// 挑战内容 需要回答 sha256(nonce + 数据[offset..offset+size])
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadChallenge {
+    pub nonce: HashDigest,
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl UploadChallenge {
+    // 挑战的回答
+    pub fn answer(nonce: &HashDigest, data: &[u8]) -> HashDigest {
+        use sha2::Digest;
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(nonce.0);
+        hasher.update(data);
+        HashDigest(hasher.finalize().into())
+    }
+}
+
+// 证明持有数据的参数
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct UploadProofArg {
+    pub nonce: HashDigest, // 挑战的随机数
+    pub path: String,
+    pub headers: Vec<(String, String)>, // 使用的 header
+    pub proof: HashDigest,              // 挑战的回答
+}
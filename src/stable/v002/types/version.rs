@@ -0,0 +1,137 @@
+use candid::CandidType;
+use ic_canister_kit::common::trap;
+use ic_canister_kit::functions::stable::{from_bytes, to_bytes};
+use ic_canister_kit::stable::{Bound, Cow, Storable};
+use ic_canister_kit::types::*;
+use serde::{Deserialize, Serialize};
+
+use super::{AssetFile, AssetStatus, HashDigest};
+
+// ============================== 历史版本 ==============================
+
+// 默认保留的历史版本数量
+pub const DEFAULT_MAX_VERSIONS: u32 = 5;
+// 最多保留的历史版本数量
+pub const MAX_VERSIONS_LIMIT: u32 = 100;
+
+// 历史版本的保留策略
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRetention {
+    pub max_versions: u32,              // 每个文件最多保留的历史版本数量, 0 表示不保留
+    pub max_age: Option<DurationNanos>, // 被覆盖超过该时间的历史版本会被清除
+}
+
+impl Default for VersionRetention {
+    fn default() -> Self {
+        Self {
+            max_versions: DEFAULT_MAX_VERSIONS,
+            max_age: None,
+        }
+    }
+}
+
+// 被覆盖的文件内容
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileVersion {
+    pub version: u64, // 同一路径下递增的版本号
+    pub hash: HashDigest,
+    pub size: u64,
+    pub headers: Vec<(String, String)>,
+    pub modified: TimestampNanos,
+    pub uploader: Option<UserId>,
+    pub archived: TimestampNanos, // 被覆盖的时间
+}
+
+impl FileVersion {
+    // 历史版本对应的文件, 路径不变
+    pub fn to_file(&self, path: String, created: TimestampNanos, status: AssetStatus) -> AssetFile {
+        AssetFile {
+            path,
+            created,
+            modified: self.modified,
+            headers: self.headers.clone(),
+            hash: self.hash,
+            size: self.size,
+            status,
+            uploader: self.uploader,
+            expires_at: None, // 沿用当前文件的过期时间
+        }
+    }
+}
+
+// 同一路径的历史版本, 按版本号从小到大排列
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FileVersions {
+    pub next: u64, // 最新的版本号
+    pub list: Vec<FileVersion>,
+}
+
+impl FileVersions {
+    pub fn get(&self, version: u64) -> Option<&FileVersion> {
+        self.list.iter().find(|v| v.version == version)
+    }
+    // 按保留策略清除历史版本, 返回被清除的版本
+    pub fn retain(&mut self, retention: &VersionRetention, now: TimestampNanos) -> Vec<FileVersion> {
+        let mut removed = Vec::new();
+        while (retention.max_versions as usize) < self.list.len() {
+            removed.push(self.list.remove(0));
+        }
+        if let Some(max_age) = retention.max_age {
+            let (expired, kept) = std::mem::take(&mut self.list)
+                .into_iter()
+                .partition(|v| (max_age.into_inner() as i128) < now.into_inner() - v.archived.into_inner());
+            self.list = kept;
+            removed.extend::<Vec<_>>(expired);
+        }
+        removed
+    }
+}
+
+// 历史版本在 hashes 中的引用, 不以 / 开头, 不会和文件路径冲突
+pub fn version_ref(path: &str, version: u64) -> String {
+    format!("{version}:{path}")
+}
+
+// 对外的历史版本
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct QueryFileVersion {
+    pub version: u64,
+    pub hash: String,
+    pub size: u64,
+    pub headers: Vec<(String, String)>,
+    pub modified: TimestampNanos,
+    pub uploader: Option<UserId>,
+    pub archived: TimestampNanos,
+}
+
+impl From<FileVersion> for QueryFileVersion {
+    fn from(version: FileVersion) -> Self {
+        QueryFileVersion {
+            version: version.version,
+            hash: version.hash.hex(),
+            size: version.size,
+            headers: version.headers,
+            modified: version.modified,
+            uploader: version.uploader,
+            archived: version.archived,
+        }
+    }
+}
+
+// ============================== 稳定内存存储 ==============================
+
+impl Storable for FileVersions {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(trap(to_bytes(self)))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        trap(to_bytes(&self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        trap(from_bytes(&bytes))
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
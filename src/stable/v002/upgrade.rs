@@ -0,0 +1,175 @@
+use super::super::v001::types::{
+    CanisterKit as LastCanisterKit, HashDigest as LastHashDigest, InnerState as LastState,
+    UploadingFile as LastUploadingFile,
+};
+
+use super::types::*;
+
+impl From<Box<LastState>> for Box<InnerState> {
+    fn from(value: Box<LastState>) -> Self {
+        let mut state = InnerState::default(); // ? 初始化
+
+        // ! 每次升级新版本，务必比较每一个数据的升级方式
+        // ! 如果不修改数据结构，可以直接赋值升级
+        // ! 如果修改数据结构，必须代码处理数据升级
+
+        // 1. 继承之前的数据
+        let LastState {
+            canister_kit,
+            hashed,
+            assets,
+            files,
+            uploading,
+            ..
+        } = *value;
+        let LastCanisterKit {
+            pause,
+            permissions,
+            records,
+            schedule,
+        } = canister_kit;
+        state.canister_kit.pause = pause;
+        state.canister_kit.permissions = permissions;
+        state.canister_kit.records = records;
+        state.canister_kit.schedule = schedule;
+
+        state.hashed = hashed;
+
+        // 2. 文件索引转存到稳定内存, hashes 可以由文件索引重建
+        for hash in assets.into_keys() {
+            let hash = hash_digest(hash);
+            state.assets.insert(hash, AssetData {});
+            if hashed {
+                state.verifying.insert(hash); // 旧版本可能直接采用了上传的 hash, 需要后台校验
+            }
+        }
+        for (path, file) in files {
+            let hash = hash_digest(file.hash);
+            state.files.insert(
+                path.clone(),
+                AssetFile {
+                    path: file.path,
+                    created: file.created,
+                    modified: file.modified,
+                    headers: file.headers,
+                    hash,
+                    size: file.size,
+                    status: AssetStatus::Committed, // 旧版本只有写入完成的文件
+                    uploader: None,
+                    expires_at: None,
+                },
+            );
+            state.put_hash(hash, path);
+        }
+
+        // 3. 上传中的数据, 旧版本暂存在堆内存的数据要转存到稳定内存
+        for (path, last) in uploading {
+            state.next_session += 1;
+            let file = uploading_file(state.next_session, &last);
+            for (index, chunk) in last.data.chunks(last.chunk_size as usize).enumerate() {
+                if last.chunked.get(index).is_some_and(|chunked| *chunked) {
+                    file.write(index as u32, chunk.to_vec());
+                }
+            }
+            state.uploading.insert(path, file);
+        }
+
+        Box::new(state)
+    }
+}
+
+fn hash_digest(hash: LastHashDigest) -> HashDigest {
+    hash.0.into()
+}
+
+fn uploading_file(session: u64, file: &LastUploadingFile) -> UploadingFile {
+    UploadingFile {
+        session,
+        path: file.path.clone(),
+        headers: file.headers.clone(),
+        hash: hash_digest(file.hash),
+        size: file.size,
+        chunk_size: file.chunk_size,
+        chunks: file.chunks,
+        chunked: file.chunked.clone(),
+        created: 0.into(),
+        touched: 0.into(), // 旧版本没有记录时间, 第一次检查过期时再开始计算
+        expires_at: None,
+        uploader: None, // 旧版本没有记录
+        failed: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::v001::types as v001_types;
+    use super::*;
+
+    fn migrate(last_state: LastState) -> Box<InnerState> {
+        Box::new(last_state).into()
+    }
+
+    #[test]
+    fn should_move_indexes_to_stable_memory() {
+        let mut last_state = LastState::default();
+        let hash = v001_types::HashDigest([0; 32]);
+        last_state.assets.insert(hash, v001_types::AssetData {});
+        for path in ["/a.txt", "/b.txt"] {
+            last_state.files.insert(
+                path.to_string(),
+                v001_types::AssetFile {
+                    path: path.to_string(),
+                    created: 1.into(),
+                    modified: 2.into(),
+                    headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+                    hash,
+                    size: 3,
+                },
+            );
+        }
+
+        let state = migrate(last_state);
+
+        let hash = hash_digest(hash);
+        assert!(state.assets.contains_key(&hash));
+        assert_eq!(state.files.len(), 2);
+        let file = state.files.get(&"/a.txt".to_string());
+        assert!(file.is_some_and(|file| file.hash == hash && file.size == 3 && file.modified == 2.into()));
+        assert_eq!(state.files(&UserId::anonymous()).len(), 2);
+    }
+
+    #[test]
+    fn should_move_heap_staged_uploads_to_stable_memory() {
+        let mut last_state = LastState::default();
+        let data = (0..10u8).collect::<Vec<_>>();
+        let mut staged = vec![0; 10];
+        staged[..4].copy_from_slice(&data[..4]); // 只上传了第一块
+        staged[8..].copy_from_slice(&data[8..]); // 和最后一块
+        last_state.uploading.insert(
+            "/staged.bin".to_string(),
+            LastUploadingFile {
+                path: "/staged.bin".to_string(),
+                headers: vec![],
+                hash: v001_types::HashDigest([1; 32]),
+                data: staged,
+                size: 10,
+                chunk_size: 4,
+                chunks: 3,
+                chunked: vec![true, false, true],
+            },
+        );
+
+        let state = migrate(last_state);
+
+        let file = state.uploading.get("/staged.bin");
+        assert!(file.is_some_and(|file| file.session == 1 && file.chunked == vec![true, false, true]));
+        let Some(file) = file else { return };
+        assert_eq!(file.read(0, 4), data[..4]);
+        assert_eq!(file.read(8, 10), data[8..]);
+
+        // 补齐剩下的块, 数据可以完整读出
+        let mut file = file.clone();
+        assert!(file.put(1, data[4..8].to_vec()).is_ok());
+        assert_eq!(file.read(0, 10), data);
+    }
+}
@@ -38,8 +38,160 @@ fn test_business_apis() {
     assert!(alice.business_download("/456.txt".to_string()).unwrap_err().reject_message.contains("File not found"));
 
     // 🚩 2 business upload
-    assert_eq!(alice.business_upload(vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: "/123.txt".to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3 }]).unwrap_err().reject_message, "Permission 'BusinessUpload' is required".to_string());
-    assert_eq!(default.business_upload(vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: "/123.txt".to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3 }]).unwrap(), ());
+    assert_eq!(alice.business_upload(vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: "/123.txt".to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3, expires_at: None }]).unwrap_err().reject_message, "Permission 'BusinessUpload' is required".to_string());
+    assert_eq!(default.business_upload(vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: "/123.txt".to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3, expires_at: None }]).unwrap(), ());
     assert_eq!(alice.business_files().unwrap().pop().unwrap().hash, "039058c6f2c0cb492c533b0a4d14ef77cc0f78abccced5287d84a1a2011cfb81".to_string());
     assert_eq!(alice.business_download("/123.txt".to_string()).unwrap(), vec![1, 2, 3]);
+
+    let page = alice.business_files_by_page(QueryPage { page: 1, size: 10 }, Some("/1".to_string()), None, None).unwrap().unwrap();
+    assert_eq!((page.page.total, page.page.data.len(), page.next), (1, 1, None));
+    assert_eq!(alice.business_files_by_page(QueryPage { page: 1, size: 10 }, Some("/4".to_string()), None, Some(FileSort::PathDesc)).unwrap().unwrap().page.total, 0);
+
+    // 🚩 2.1 business upload session
+    let data = (0..=255u8).cycle().take(1024 * 64 + 1).collect::<Vec<_>>(); // 至少 64K 才能分块
+    let hash = { use sha2::Digest; sha2::Sha256::digest(&data).to_vec() };
+    assert_eq!(default.business_upload_begin_v2(UploadBeginArg { hash: hash.clone().into(), path: "/456.txt".to_string(), size: data.len() as u64, headers: vec![], chunk_size: 2, expires_at: None }).unwrap(), Err(StorageError::InvalidArgument("chunk size must be at least 65536 unless the file has only one chunk".to_string())));
+    let begin = UploadBeginArg { hash: hash.into(), path: "/456.txt".to_string(), size: data.len() as u64, headers: vec![], chunk_size: 1024 * 64, expires_at: None };
+    assert_eq!(alice.business_upload_begin(begin.clone()).unwrap_err().reject_message, "Permission 'BusinessUpload' is required".to_string());
+    let session = default.business_upload_begin(begin.clone()).unwrap();
+    assert_eq!(default.business_upload_begin(begin).unwrap(), session);
+    assert_eq!(default.business_upload_put(vec![UploadChunkArg { session, index: 1, chunk: data[1024 * 64..].to_vec().into() }]).unwrap(), ());
+    assert_eq!(default.business_upload_status(session).unwrap().unwrap().chunked, vec![false, true]);
+    assert!(default.business_upload_commit(session).unwrap_err().reject_message.contains("upload is not finished"));
+    assert_eq!(default.business_upload_put(vec![UploadChunkArg { session, index: 0, chunk: data[..1024 * 64].to_vec().into() }]).unwrap(), ());
+    assert_eq!(default.business_upload_commit(session).unwrap(), "/456.txt".to_string());
+    assert_eq!(default.business_upload_status(session).unwrap(), None);
+    assert_eq!(alice.business_download("/456.txt".to_string()).unwrap(), data);
+    assert_eq!(default.business_delete(vec!["/456.txt".to_string()]).unwrap(), ());
+
+    // 🚩 2.2 business typed errors
+    assert_eq!(alice.business_upload_put_v2(vec![]).unwrap(), Err(StorageError::PermissionDenied("BusinessUpload".to_string())));
+    assert_eq!(default.business_upload_commit_v2(session).unwrap(), Err(StorageError::SessionNotFound(session)));
+    assert_eq!(default.business_upload_begin_v2(UploadBeginArg { hash: vec![0; 32].into(), path: "456.txt".to_string(), size: 3, headers: vec![], chunk_size: 3, expires_at: None }).unwrap(), Err(StorageError::InvalidPath("path must start with /".to_string())));
+    assert_eq!(default.business_upload_begin_v2(UploadBeginArg { hash: vec![0; 32].into(), path: "/456.txt".to_string(), size: 3, headers: vec![], chunk_size: 3, expires_at: Some(1.into()) }).unwrap(), Err(StorageError::InvalidArgument("expires at must be in the future".to_string())));
+    assert_eq!(alice.business_download_v2("/456.txt".to_string()).unwrap(), Err(StorageError::NotFound("/456.txt".to_string())));
+    assert_eq!(alice.business_download_by_v2("/123.txt".to_string(), 1, 3, None).unwrap(), Err(StorageError::OutOfRange { total: 3, size: 3, offset: 1 }));
+    assert_eq!(alice.business_download_by_v2("/123.txt".to_string(), 1, 2, None).unwrap(), Ok(vec![2, 3].into()));
+
+    // 🚩 2.3 business move, copy and update headers
+    assert_eq!(alice.business_copy("/123.txt".to_string(), "/copy/123.txt".to_string()).unwrap(), Err(StorageError::PermissionDenied("BusinessUpload".to_string())));
+    assert_eq!(default.business_copy("/123.txt".to_string(), "/copy/123.txt".to_string()).unwrap(), Ok(()));
+    assert_eq!(default.business_copy("/123.txt".to_string(), "/copy/123.txt".to_string()).unwrap(), Err(StorageError::AlreadyExists("/copy/123.txt".to_string())));
+    assert_eq!(default.business_move("/copy/".to_string(), "/moved/".to_string(), false).unwrap(), Ok(()));
+    assert_eq!(alice.business_download("/moved/123.txt".to_string()).unwrap(), vec![1, 2, 3]);
+    assert_eq!(default.business_move("/moved/123.txt".to_string(), "/123.txt".to_string(), false).unwrap(), Err(StorageError::AlreadyExists("/123.txt".to_string())));
+    assert_eq!(default.business_delete(vec!["/moved/123.txt".to_string()]).unwrap(), ());
+    assert_eq!(alice.business_update_headers("/123.txt".to_string(), vec![]).unwrap(), Err(StorageError::PermissionDenied("BusinessUpload".to_string())));
+    assert_eq!(default.business_update_headers("/123.txt".to_string(), vec![("Content-Type".to_string(), "text/plain".to_string())]).unwrap(), Ok(1));
+    assert_eq!(alice.business_files().unwrap().pop().unwrap().headers, vec![("Content-Type".to_string(), "text/plain".to_string())]);
+
+    // 🚩 2.4 business file versions
+    assert_eq!(alice.business_version_retention().unwrap(), Ok(VersionRetention { max_versions: 5, max_age: None }));
+    let versions = alice.business_file_versions("/123.txt".to_string()).unwrap().unwrap();
+    assert_eq!(versions.iter().map(|v| (v.version, v.headers.len())).collect::<Vec<_>>(), vec![(1, 0)]);
+    assert_eq!(alice.business_download_by("/123.txt".to_string(), 0, 3, Some(1)).unwrap(), vec![1, 2, 3]);
+    assert_eq!(alice.business_restore_version("/123.txt".to_string(), 1).unwrap(), Err(StorageError::PermissionDenied("BusinessUpload".to_string())));
+    assert_eq!(default.business_restore_version("/123.txt".to_string(), 2).unwrap(), Err(StorageError::NotFound("/123.txt?version=2".to_string())));
+    assert_eq!(default.business_restore_version("/123.txt".to_string(), 1).unwrap(), Ok(()));
+    assert_eq!(alice.business_files().unwrap().pop().unwrap().headers, vec![]);
+    assert_eq!(default.business_version_retention_update(VersionRetention { max_versions: 101, max_age: None }).unwrap(), Err(StorageError::InvalidArgument("max versions must less than 100".to_string())));
+
+    // 🚩 3 business delete
+    let dir = alice.business_list_dir("/".to_string(), None, 10).unwrap().unwrap();
+    assert_eq!((dir.path, dir.dirs.len(), dir.files.len(), dir.next), ("/".to_string(), 0, 1, None));
+    assert_eq!(alice.business_delete_dir("/".to_string(), true).unwrap(), Err(StorageError::PermissionDenied("BusinessDelete".to_string())));
+    assert_eq!(default.business_delete_dir("tmp".to_string(), true).unwrap(), Err(StorageError::InvalidPath("path must start with /".to_string())));
+    assert_eq!(default.business_delete_dir("/tmp".to_string(), true).unwrap(), Ok(()));
+    assert_eq!(alice.business_purge_orphans().unwrap_err().reject_message, "Permission 'BusinessDelete' is required".to_string());
+    assert_eq!(default.business_purge_orphans().unwrap(), 0);
+    assert_eq!(default.business_delete(vec!["/123.txt".to_string()]).unwrap(), ());
+    assert!(alice.business_download("/123.txt".to_string()).unwrap_err().reject_message.contains("File not found"));
+    assert_eq!(default.business_purge_orphans().unwrap(), 0);
+
+    // 🚩 3.1 business trash
+    let trash = alice.business_trash_list(QueryPage { page: 1, size: 10 }).unwrap().unwrap();
+    assert_eq!(trash.data.iter().map(|t| (t.id, t.file.path.as_str())).collect::<Vec<_>>(), vec![(3, "/123.txt"), (2, "/moved/123.txt"), (1, "/456.txt")]);
+    assert_eq!(alice.business_trash_restore(3, None).unwrap(), Err(StorageError::PermissionDenied("BusinessDelete".to_string())));
+    assert_eq!(default.business_trash_restore(3, None).unwrap(), Ok("/123.txt".to_string()));
+    assert_eq!(alice.business_download("/123.txt".to_string()).unwrap(), vec![1, 2, 3]);
+    assert_eq!(default.business_trash_restore(2, Some("/123.txt".to_string())).unwrap(), Err(StorageError::AlreadyExists("/123.txt".to_string())));
+    assert_eq!(default.business_trash_empty(Some(vec![1])).unwrap(), Ok(()));
+    assert_eq!(default.business_trash_empty(None).unwrap(), Ok(()));
+    assert_eq!(alice.business_trash_list(QueryPage { page: 1, size: 10 }).unwrap().unwrap().total, 0);
+
+    // 🚩 4 business acl
+    let private = PathAcl { read: AccessRule::Restricted { users: vec![bob_identity], roles: vec![] }, delete: None, upload: Some(AccessRule::Restricted { users: vec![bob_identity], roles: vec![] }) };
+    assert_eq!(alice.permission_acl_update("/private/".to_string(), Some(private.clone())).unwrap(), Err(StorageError::PermissionDenied("PermissionUpdate".to_string())));
+    assert_eq!(default.permission_acl_update("private/".to_string(), Some(private.clone())).unwrap(), Err(StorageError::InvalidPath("path must start with /".to_string())));
+    assert_eq!(default.permission_acl_update("/private/".to_string(), Some(private.clone())).unwrap(), Ok(()));
+    assert_eq!(default.permission_acl_find().unwrap(), Ok(vec![("/private/".to_string(), private)]));
+    let upload = |path: &str| vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: path.to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3, expires_at: None }];
+    assert_eq!(default.business_upload_v2(upload("/private/a.txt")).unwrap(), Err(StorageError::PermissionDenied("BusinessUpload".to_string())));
+    assert!(default.business_upload(upload("/private/a.txt")).is_err()); // 旧接口也要检查路径的访问控制
+    assert!(default.business_delete(vec!["/private/a.txt".to_string()]).is_ok()); // 没有删除规则, 按全局权限
+    assert_eq!(default.permission_acl_update("/private/".to_string(), None).unwrap(), Ok(()));
+    assert_eq!(default.permission_acl_find().unwrap(), Ok(vec![]));
+
+    // 🚩 5 business quota
+    let quota = StorageQuota { max_bytes: Some(5), max_files: None };
+    assert_eq!(alice.business_quota_update(QuotaTarget::User(default_identity), Some(quota)).unwrap(), Err(StorageError::PermissionDenied("PermissionUpdate".to_string())));
+    assert_eq!(default.business_quota_update(QuotaTarget::User(default_identity), Some(quota)).unwrap(), Ok(()));
+    assert_eq!(default.business_usage_query(None).unwrap(), Ok(QueryUsage { user: default_identity, quota: Some(quota), uploading: StorageUsage { files: 0, bytes: 0 }, usage: StorageUsage { files: 1, bytes: 3 } }));
+    assert_eq!(alice.business_usage_query(Some(default_identity)).unwrap(), Err(StorageError::PermissionDenied("PermissionFind".to_string())));
+    assert_eq!(default.business_upload_begin_v2(UploadBeginArg { hash: vec![0; 32].into(), path: "/789.txt".to_string(), size: 3, headers: vec![], chunk_size: 3, expires_at: None }).unwrap(), Err(StorageError::QuotaExceeded { used: 6, limit: 5 }));
+    assert!(default.business_upload_begin_v2(UploadBeginArg { hash: vec![0; 32].into(), path: "/123.txt".to_string(), size: 5, headers: vec![], chunk_size: 3, expires_at: None }).unwrap().is_ok());
+    assert_eq!(default.business_quota_update(QuotaTarget::User(default_identity), None).unwrap(), Ok(()));
+
+    // 🚩 6 business upload token
+    let expires_at: candid::Int = (pic.get_time().as_nanos_since_unix_epoch() as i128 + 60_000_000_000).into();
+    assert_eq!(alice.business_upload_token_mint(UploadTokenArg { prefix: "/avatars/".to_string(), max_size: 3, expires_at: expires_at.clone() }).unwrap(), Err(StorageError::PermissionDenied("BusinessUpload".to_string())));
+    let token = default.business_upload_token_mint(UploadTokenArg { prefix: "/avatars/".to_string(), max_size: 3, expires_at: expires_at.clone() }).unwrap().unwrap();
+    assert_eq!(default.business_upload_tokens().unwrap().unwrap().iter().map(|t| (t.id, t.path.clone())).collect::<Vec<_>>(), vec![(token.id, None)]);
+    assert_eq!(anonymous.business_upload_by_token(token.token.clone(), vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: "/other.txt".to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3, expires_at: None }]).unwrap(), Err(StorageError::InvalidPath("path must start with /avatars/".to_string())));
+    assert_eq!(anonymous.business_upload_by_token(token.token.clone(), vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: "/avatars/a.txt".to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3, expires_at: None }]).unwrap(), Ok(()));
+    assert_eq!(alice.business_download("/avatars/a.txt".to_string()).unwrap(), vec![1, 2, 3]);
+    assert_eq!(anonymous.business_upload_by_token(token.token, vec![UploadingArg { hash: vec![0; 32].into(), chunk: vec![1, 2, 3].into(), path: "/avatars/a.txt".to_string(), size: 3, headers: vec![], index: 0, chunk_size: 3, expires_at: None }]).unwrap(), Err(StorageError::InvalidToken("upload token not found or expired".to_string())));
+    let token = default.business_upload_token_mint(UploadTokenArg { prefix: "/avatars/".to_string(), max_size: 3, expires_at: expires_at.clone() }).unwrap().unwrap();
+    assert_eq!(default.business_upload_token_revoke(token.id).unwrap(), Ok(()));
+    assert_eq!(default.business_upload_tokens().unwrap().unwrap(), vec![]);
+
+    // 🚩 7 business signed url
+    let private = PathAcl { read: AccessRule::Restricted { users: vec![default_identity], roles: vec![] }, delete: None, upload: None };
+    assert_eq!(default.permission_acl_update("/avatars/".to_string(), Some(private)).unwrap(), Ok(()));
+    let http_get = |url: &str| CustomHttpRequest { url: url.to_string(), method: "GET".to_string(), body: vec![].into(), headers: vec![] };
+    assert_eq!(anonymous.http_request(http_get("/avatars/a.txt")).unwrap().status_code, 403);
+    assert_eq!(alice.business_sign_url("/avatars/a.txt".to_string(), 60_000_000_000_u64.into(), None).unwrap(), Err(StorageError::PermissionDenied("BusinessQuery".to_string())));
+    assert_eq!(default.business_sign_url("/avatars/a.txt".to_string(), 0_u64.into(), None).unwrap(), Err(StorageError::InvalidArgument("ttl must be between 1 and 604800000000000".to_string())));
+    let signed = default.business_sign_url("/avatars/a.txt".to_string(), 60_000_000_000_u64.into(), None).unwrap().unwrap();
+    assert_eq!(anonymous.http_request(http_get(&signed.url)).unwrap().body.to_vec(), vec![1, 2, 3]);
+    assert_eq!(anonymous.http_request(http_get(&signed.url.replace("exp=", "exp=1"))).unwrap().status_code, 403);
+    pic.advance_time(std::time::Duration::from_secs(120));
+    assert_eq!(anonymous.http_request(http_get(&signed.url)).unwrap().status_code, 403);
+
+    // 🚩 8 business http upload
+    let http_put = |url: &str, token: &str| CustomHttpRequest { url: url.to_string(), method: "PUT".to_string(), body: vec![4, 5, 6].into(), headers: vec![("Authorization".to_string(), format!("Bearer {token}")), ("Content-Type".to_string(), "text/plain".to_string()), ("X-Secret".to_string(), "1".to_string())] };
+    assert_eq!(anonymous.http_request(http_put("/avatars/b.txt", "")).unwrap().upgrade, Some(true));
+    assert_eq!(anonymous.http_request_update(http_put("/avatars/b.txt", "wrong")).unwrap().status_code, 401);
+    let token = default.business_upload_token_mint(UploadTokenArg { prefix: "/avatars/".to_string(), max_size: 3, expires_at: (pic.get_time().as_nanos_since_unix_epoch() as i128 + 60_000_000_000).into() }).unwrap().unwrap();
+    assert_eq!(anonymous.http_request_update(http_put("/other.txt", &token.token)).unwrap().status_code, 400);
+    assert_eq!(anonymous.http_request_update(http_put("/avatars/b.txt", &token.token)).unwrap().status_code, 201);
+    assert_eq!(default.business_download("/avatars/b.txt".to_string()).unwrap(), vec![4, 5, 6]);
+    assert_eq!(default.business_files().unwrap().into_iter().find(|file| file.path == "/avatars/b.txt").unwrap().headers, vec![("content-type".to_string(), "text/plain".to_string())]);
+    assert_eq!(anonymous.http_request_update(http_put("/avatars/b.txt", &token.token)).unwrap().status_code, 401);
+
+    // 🚩 9 business http methods
+    let http_method = |method: &str, headers: Vec<(String, String)>| CustomHttpRequest { url: "/123.txt".to_string(), method: method.to_string(), body: vec![].into(), headers };
+    let get = anonymous.http_request(http_method("GET", vec![])).unwrap();
+    assert_eq!((get.status_code, get.body.to_vec(), get.upgrade), (200, vec![1, 2, 3], None));
+    assert!(get.headers.iter().any(|(name, _)| name == "IC-Certificate")); // 公开的文件有证书
+    let range = anonymous.http_request(http_method("GET", vec![("Range".to_string(), "bytes=0-0".to_string())])).unwrap();
+    assert_eq!((range.status_code, range.body.to_vec(), range.upgrade), (206, vec![1], None)); // 范围请求没有证书, 直接返回
+    assert!(!range.headers.iter().any(|(name, _)| name == "IC-Certificate"));
+    let head = anonymous.http_request(http_method("HEAD", vec![])).unwrap();
+    assert_eq!((head.status_code, head.body.to_vec(), head.headers.iter().find(|(name, _)| name == "Content-Length").map(|(_, value)| value.clone())), (200, vec![], Some("3".to_string())));
+    let options = anonymous.http_request(http_method("OPTIONS", vec![])).unwrap();
+    assert_eq!((options.status_code, options.body.to_vec()), (204, vec![]));
+    assert!(options.headers.contains(&("Access-Control-Allow-Origin".to_string(), "*".to_string())));
+    assert_eq!(anonymous.http_request(http_method("DELETE", vec![])).unwrap().status_code, 405);
+    assert_eq!(anonymous.http_request_update(http_method("GET", vec![])).unwrap().status_code, 405);
 }
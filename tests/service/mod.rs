@@ -13,22 +13,223 @@ pub struct InitArg {
     pub schedule: Option<candid::Nat>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct InitArg1 {
+    pub supers: Option<Vec<Principal>>,
+    pub schedule: Option<candid::Nat>,
+    pub uploading_timeout: Option<candid::Nat>,
+    pub trash_retention: Option<candid::Nat>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
 pub enum InitArgs {
     V0(InitArg),
     V1(InitArg),
+    V2(InitArg1),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum AssetStatus {
+    Committed,
+    Mismatched,
+    Committing,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QueryDirEntry {
+    pub name: String,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QueryDir {
+    pub files: Vec<QueryFile>,
+    pub dirs: Vec<QueryDirEntry>,
+    pub next: Option<String>,
+    pub path: String,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QueryFileVersion {
+    pub modified: candid::Int,
+    pub hash: String,
+    pub size: u64,
+    pub headers: Vec<(String, String)>,
+    pub version: u64,
+    pub uploader: Option<Principal>,
+    pub archived: candid::Int,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct VersionRetention {
+    pub max_versions: u32,
+    pub max_age: Option<candid::Nat>,
+}
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
 pub struct QueryFile {
+    pub status: AssetStatus,
     pub created: candid::Int,
     pub modified: candid::Int,
     pub hash: String,
     pub path: String,
     pub size: u64,
     pub headers: Vec<(String, String)>,
+    pub expires_at: Option<candid::Int>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UploadBeginArg {
+    pub hash: serde_bytes::ByteBuf,
+    pub path: String,
+    pub size: u64,
+    pub headers: Vec<(String, String)>,
+    pub chunk_size: u32,
+    pub expires_at: Option<candid::Int>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum FileSort {
+    PathDesc,
+    PathAsc,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct FilePageData {
+    pub total: u64,
+    pub data: Vec<QueryFile>,
+    pub page: u64,
+    pub size: u32,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UploadTokenArg {
+    pub max_size: u64,
+    pub prefix: String,
+    pub expires_at: candid::Int,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UploadToken {
+    pub id: u64,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QueryUploadToken {
+    pub id: u64,
+    pub max_size: u64,
+    pub path: Option<String>,
+    pub issuer: Principal,
+    pub prefix: String,
+    pub expires_at: candid::Int,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct SignedUrl {
+    pub url: String,
+    pub expires_at: candid::Int,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct StorageUsage {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize)]
+pub struct StorageQuota {
+    pub max_bytes: Option<u64>,
+    pub max_files: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QueryUsage {
+    pub user: Principal,
+    pub quota: Option<StorageQuota>,
+    pub uploading: StorageUsage,
+    pub usage: StorageUsage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum QuotaTarget {
+    Role(String),
+    User(Principal),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QueryTrashedFile {
+    pub id: u64,
+    pub deleted: candid::Int,
+    pub deleter: Option<Principal>,
+    pub file: QueryFile,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct TrashPageData {
+    pub total: u64,
+    pub data: Vec<QueryTrashedFile>,
+    pub page: u64,
+    pub size: u32,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QueryFilePage {
+    pub next: Option<String>,
+    pub page: FilePageData,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum StorageError {
+    Internal(String),
+    Paused(String),
+    TooLarge { max: u64, size: u64 },
+    ChunkIndexOutOfRange { index: u32, chunks: u32 },
+    SessionNotFound(u64),
+    SizeMismatch { actual: u64, expected: u64 },
+    InvalidHeader(String),
+    NotFound(String),
+    PermissionDenied(String),
+    ChallengeNotFound,
+    AlreadyExists(String),
+    InvalidPath(String),
+    ProofMismatch,
+    HashMismatch { actual: String, expected: String },
+    UploadNotFinished { uploaded: u32, chunks: u32 },
+    InvalidArgument(String),
+    OutOfRange { total: u64, size: u64, offset: u64 },
+    Committing(String),
+    QuotaExceeded { used: u64, limit: u64 },
+    InvalidToken(String),
+    ChunkLengthMismatch { actual: u64, expected: u64, index: u32 },
+}
+pub type StorageResult<T> = std::result::Result<T, StorageError>;
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UploadChallenge {
+    pub size: u64,
+    pub offset: u64,
+    pub nonce: serde_bytes::ByteBuf,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UploadChunkArg {
+    pub chunk: serde_bytes::ByteBuf,
+    pub session: u64,
+    pub index: u32,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UploadProofArg {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub nonce: serde_bytes::ByteBuf,
+    pub proof: serde_bytes::ByteBuf,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UploadingStatus {
+    pub created: candid::Int,
+    pub path: String,
+    pub size: u64,
+    pub chunked: Vec<bool>,
+    pub session: u64,
+    pub uploaded: u64,
+    pub chunks: u32,
+    pub chunk_size: u32,
+    pub touched: candid::Int,
+    pub failed: Option<StorageError>,
+    pub committing: bool,
+}
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum UploadCommitted {
+    Committed(String),
+    Pending(String),
+}
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
 pub struct UploadingArg {
     pub hash: serde_bytes::ByteBuf,
@@ -38,6 +239,7 @@ pub struct UploadingArg {
     pub headers: Vec<(String, String)>,
     pub index: u32,
     pub chunk_size: u32,
+    pub expires_at: Option<candid::Int>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
@@ -165,6 +367,20 @@ pub enum PermissionUpdatedArg {
     UpdateUserRole(Principal, Option<Vec<String>>),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum AccessRule {
+    Authenticated,
+    Public,
+    Restricted { users: Vec<Principal>, roles: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct PathAcl {
+    pub read: AccessRule,
+    pub delete: Option<AccessRule>,
+    pub upload: Option<AccessRule>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
 pub struct QueryPage {
     pub page: u64,
@@ -253,6 +469,12 @@ impl Service<'_> {
     pub fn canister_status(&self) -> Result<CanisterStatusResult> {
         self.update_call("canister_status", Encode!(&()).unwrap())
     }
+    pub fn http_request(&self, arg0: CustomHttpRequest) -> Result<CustomHttpResponse> {
+        self.query_call("http_request", encode_one(&arg0).unwrap())
+    }
+    pub fn http_request_update(&self, arg0: CustomHttpRequest) -> Result<CustomHttpResponse> {
+        self.update_call("http_request_update", encode_one(&arg0).unwrap())
+    }
     pub fn pause_query(&self) -> Result<bool> {
         self.query_call("pause_query", Encode!(&()).unwrap())
     }
@@ -262,6 +484,12 @@ impl Service<'_> {
     pub fn pause_replace(&self, arg0: Option<String>) -> Result<()> {
         self.update_call("pause_replace", encode_one(arg0).unwrap())
     }
+    pub fn permission_acl_find(&self) -> Result<StorageResult<Vec<(String, PathAcl)>>> {
+        self.query_call("permission_acl_find", Encode!(&()).unwrap())
+    }
+    pub fn permission_acl_update(&self, arg0: String, arg1: Option<PathAcl>) -> Result<StorageResult<()>> {
+        self.update_call("permission_acl_update", encode_args((&arg0, &arg1)).unwrap())
+    }
     pub fn permission_all(&self) -> Result<Vec<Permission>> {
         self.query_call("permission_all", Encode!(&()).unwrap())
     }
@@ -322,25 +550,189 @@ impl Service<'_> {
 
     // ======================= business apis =======================
 
+    pub fn business_copy(&self, arg0: String, arg1: String) -> Result<StorageResult<()>> {
+        self.update_call("business_copy", encode_args((&arg0, &arg1)).unwrap())
+    }
     pub fn business_delete(&self, arg0: Vec<String>) -> Result<()> {
         self.update_call("business_delete", encode_one(&arg0).unwrap())
     }
+    pub fn business_delete_dir(&self, arg0: String, arg1: bool) -> Result<StorageResult<()>> {
+        self.update_call("business_delete_dir", encode_args((&arg0, &arg1)).unwrap())
+    }
+    pub fn business_delete_v2(&self, arg0: Vec<String>) -> Result<StorageResult<()>> {
+        self.update_call("business_delete_v2", encode_one(&arg0).unwrap())
+    }
     pub fn business_download(&self, arg0: String) -> Result<serde_bytes::ByteBuf> {
         self.query_call("business_download", encode_one(&arg0).unwrap())
     }
-    pub fn business_download_by(&self, arg0: String, arg1: u64, arg2: u64) -> Result<serde_bytes::ByteBuf> {
-        self.query_call("business_download_by", encode_args((&arg0, &arg1, &arg2)).unwrap())
+    pub fn business_download_v2(&self, arg0: String) -> Result<StorageResult<serde_bytes::ByteBuf>> {
+        self.query_call("business_download_v2", encode_one(&arg0).unwrap())
+    }
+    pub fn business_download_by(
+        &self,
+        arg0: String,
+        arg1: u64,
+        arg2: u64,
+        arg3: Option<u64>,
+    ) -> Result<serde_bytes::ByteBuf> {
+        self.query_call(
+            "business_download_by",
+            encode_args((&arg0, &arg1, &arg2, &arg3)).unwrap(),
+        )
+    }
+    pub fn business_download_by_v2(
+        &self,
+        arg0: String,
+        arg1: u64,
+        arg2: u64,
+        arg3: Option<u64>,
+    ) -> Result<StorageResult<serde_bytes::ByteBuf>> {
+        self.query_call(
+            "business_download_by_v2",
+            encode_args((&arg0, &arg1, &arg2, &arg3)).unwrap(),
+        )
+    }
+    pub fn business_file_versions(&self, arg0: String) -> Result<StorageResult<Vec<QueryFileVersion>>> {
+        self.query_call("business_file_versions", encode_one(&arg0).unwrap())
     }
     pub fn business_files(&self) -> Result<Vec<QueryFile>> {
         self.query_call("business_files", Encode!(&()).unwrap())
     }
+    pub fn business_files_by_page(
+        &self,
+        arg0: QueryPage,
+        arg1: Option<String>,
+        arg2: Option<String>,
+        arg3: Option<FileSort>,
+    ) -> Result<StorageResult<QueryFilePage>> {
+        self.query_call(
+            "business_files_by_page",
+            encode_args((&arg0, &arg1, &arg2, &arg3)).unwrap(),
+        )
+    }
+    pub fn business_files_v2(&self) -> Result<StorageResult<Vec<QueryFile>>> {
+        self.query_call("business_files_v2", Encode!(&()).unwrap())
+    }
     pub fn business_hashed_find(&self) -> Result<bool> {
         self.query_call("business_hashed_find", Encode!(&()).unwrap())
     }
+    pub fn business_hashed_find_v2(&self) -> Result<StorageResult<bool>> {
+        self.query_call("business_hashed_find_v2", Encode!(&()).unwrap())
+    }
     pub fn business_hashed_update(&self, arg0: bool) -> Result<()> {
         self.update_call("business_hashed_update", encode_one(arg0).unwrap())
     }
+    pub fn business_hashed_update_v2(&self, arg0: bool) -> Result<StorageResult<()>> {
+        self.update_call("business_hashed_update_v2", encode_one(arg0).unwrap())
+    }
+    pub fn business_list_dir(&self, arg0: String, arg1: Option<String>, arg2: u32) -> Result<StorageResult<QueryDir>> {
+        self.query_call("business_list_dir", encode_args((&arg0, &arg1, &arg2)).unwrap())
+    }
+    pub fn business_move(&self, arg0: String, arg1: String, arg2: bool) -> Result<StorageResult<()>> {
+        self.update_call("business_move", encode_args((&arg0, &arg1, &arg2)).unwrap())
+    }
+    pub fn business_purge_orphans(&self) -> Result<u64> {
+        self.update_call("business_purge_orphans", Encode!(&()).unwrap())
+    }
+    pub fn business_purge_orphans_v2(&self) -> Result<StorageResult<u64>> {
+        self.update_call("business_purge_orphans_v2", Encode!(&()).unwrap())
+    }
+    pub fn business_update_headers(&self, arg0: String, arg1: Vec<(String, String)>) -> Result<StorageResult<u64>> {
+        self.update_call("business_update_headers", encode_args((&arg0, &arg1)).unwrap())
+    }
+    pub fn business_quota_update(&self, arg0: QuotaTarget, arg1: Option<StorageQuota>) -> Result<StorageResult<()>> {
+        self.update_call("business_quota_update", encode_args((&arg0, &arg1)).unwrap())
+    }
+    pub fn business_restore_version(&self, arg0: String, arg1: u64) -> Result<StorageResult<()>> {
+        self.update_call("business_restore_version", encode_args((&arg0, &arg1)).unwrap())
+    }
+    pub fn business_sign_url(
+        &self,
+        arg0: String,
+        arg1: candid::Nat,
+        arg2: Option<u64>,
+    ) -> Result<StorageResult<SignedUrl>> {
+        self.update_call("business_sign_url", encode_args((&arg0, &arg1, &arg2)).unwrap())
+    }
+    pub fn business_trash_list(&self, arg0: QueryPage) -> Result<StorageResult<TrashPageData>> {
+        self.query_call("business_trash_list", encode_one(&arg0).unwrap())
+    }
+    pub fn business_trash_restore(&self, arg0: u64, arg1: Option<String>) -> Result<StorageResult<String>> {
+        self.update_call("business_trash_restore", encode_args((&arg0, &arg1)).unwrap())
+    }
+    pub fn business_trash_empty(&self, arg0: Option<Vec<u64>>) -> Result<StorageResult<()>> {
+        self.update_call("business_trash_empty", encode_one(&arg0).unwrap())
+    }
     pub fn business_upload(&self, arg0: Vec<UploadingArg>) -> Result<()> {
         self.update_call("business_upload", encode_one(&arg0).unwrap())
     }
+    pub fn business_upload_v2(&self, arg0: Vec<UploadingArg>) -> Result<StorageResult<()>> {
+        self.update_call("business_upload_v2", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_by_token(&self, arg0: String, arg1: Vec<UploadingArg>) -> Result<StorageResult<()>> {
+        self.update_call("business_upload_by_token", encode_args((&arg0, &arg1)).unwrap())
+    }
+    pub fn business_upload_token_mint(&self, arg0: UploadTokenArg) -> Result<StorageResult<UploadToken>> {
+        self.update_call("business_upload_token_mint", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_token_revoke(&self, arg0: u64) -> Result<StorageResult<()>> {
+        self.update_call("business_upload_token_revoke", encode_one(arg0).unwrap())
+    }
+    pub fn business_upload_tokens(&self) -> Result<StorageResult<Vec<QueryUploadToken>>> {
+        self.query_call("business_upload_tokens", Encode!(&()).unwrap())
+    }
+    pub fn business_upload_abort(&self, arg0: u64) -> Result<()> {
+        self.update_call("business_upload_abort", encode_one(arg0).unwrap())
+    }
+    pub fn business_upload_abort_v2(&self, arg0: u64) -> Result<StorageResult<()>> {
+        self.update_call("business_upload_abort_v2", encode_one(arg0).unwrap())
+    }
+    pub fn business_upload_begin(&self, arg0: UploadBeginArg) -> Result<u64> {
+        self.update_call("business_upload_begin", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_begin_v2(&self, arg0: UploadBeginArg) -> Result<StorageResult<u64>> {
+        self.update_call("business_upload_begin_v2", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_challenge(&self, arg0: serde_bytes::ByteBuf) -> Result<Option<UploadChallenge>> {
+        self.update_call("business_upload_challenge", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_challenge_v2(
+        &self,
+        arg0: serde_bytes::ByteBuf,
+    ) -> Result<StorageResult<Option<UploadChallenge>>> {
+        self.update_call("business_upload_challenge_v2", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_commit(&self, arg0: u64) -> Result<String> {
+        self.update_call("business_upload_commit", encode_one(arg0).unwrap())
+    }
+    pub fn business_upload_commit_v2(&self, arg0: u64) -> Result<StorageResult<UploadCommitted>> {
+        self.update_call("business_upload_commit_v2", encode_one(arg0).unwrap())
+    }
+    pub fn business_upload_prove(&self, arg0: UploadProofArg) -> Result<()> {
+        self.update_call("business_upload_prove", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_prove_v2(&self, arg0: UploadProofArg) -> Result<StorageResult<()>> {
+        self.update_call("business_upload_prove_v2", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_put(&self, arg0: Vec<UploadChunkArg>) -> Result<()> {
+        self.update_call("business_upload_put", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_put_v2(&self, arg0: Vec<UploadChunkArg>) -> Result<StorageResult<()>> {
+        self.update_call("business_upload_put_v2", encode_one(&arg0).unwrap())
+    }
+    pub fn business_upload_status(&self, arg0: u64) -> Result<Option<UploadingStatus>> {
+        self.query_call("business_upload_status", encode_one(arg0).unwrap())
+    }
+    pub fn business_upload_status_v2(&self, arg0: u64) -> Result<StorageResult<Option<UploadingStatus>>> {
+        self.query_call("business_upload_status_v2", encode_one(arg0).unwrap())
+    }
+    pub fn business_usage_query(&self, arg0: Option<Principal>) -> Result<StorageResult<QueryUsage>> {
+        self.query_call("business_usage_query", encode_one(arg0).unwrap())
+    }
+    pub fn business_version_retention(&self) -> Result<StorageResult<VersionRetention>> {
+        self.query_call("business_version_retention", Encode!(&()).unwrap())
+    }
+    pub fn business_version_retention_update(&self, arg0: VersionRetention) -> Result<StorageResult<()>> {
+        self.update_call("business_version_retention_update", encode_one(&arg0).unwrap())
+    }
 }